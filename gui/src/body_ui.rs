@@ -0,0 +1,442 @@
+use fieldsim::bodies::*;
+use fieldsim::conductor::*;
+use fieldsim::dielectric::*;
+use nannou::geom::Vec2;
+use nannou_egui::egui;
+use std::ops::RangeInclusive;
+
+
+
+pub trait UiConstructor<T> {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool;
+    fn get_value(&self) -> T;
+    fn reset(&mut self);
+}
+
+
+
+fn labelled_widget(ui: &mut egui::Ui, label: &str, widget: impl egui::Widget) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(widget);
+    });
+}
+
+fn labelled_drag_value(ui: &mut egui::Ui, label: &str, value: &mut f32) {
+    labelled_widget(ui, label, egui::DragValue::new(value));
+}
+
+fn labelled_slider(
+    ui: &mut egui::Ui, label: &str, value: &mut f32, range: RangeInclusive<f32>
+) {
+    labelled_widget(ui, label, egui::Slider::new(value, range));
+}
+
+
+
+#[derive(Default)]
+pub struct PointChargeConstructor {
+    x: f32, y: f32,
+    mass: f32, charge: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for PointChargeConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        labelled_drag_value(ui, "charge:", &mut self.charge);
+        labelled_drag_value(ui, "mass:", &mut self.mass);
+        
+        if self.mass < 0.1 { self.mass = 0.1; }
+        
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(PointCharge {
+            charge: self.charge,
+            mass: self.mass,
+            pos: Vec2::new(self.x, self.y),
+            vel: Vec2::ZERO
+        })
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.mass = 1.;
+        self.charge = 1.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct DipoleConstructor {
+    x: f32, y: f32, angle_deg: f32,
+    dipole: f32, mass: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for DipoleConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        labelled_slider(ui, "angle:", &mut self.angle_deg, 0.0..=360.0);
+        labelled_drag_value(ui, "dipole:", &mut self.dipole);
+        labelled_drag_value(ui, "mass:", &mut self.mass);
+
+        if self.mass < 0.1 { self.mass = 0.1; }
+        if self.dipole < 0. { self.dipole = 0.; }
+        
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(Dipole::new(
+            self.dipole, self.mass, self.angle_deg.to_radians(),
+            Vec2::new(self.x, self.y), Vec2::ZERO
+        ))
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.angle_deg = 0.;
+        self.dipole = 1.;
+        self.mass = 1.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct CircleChargeConstructor {
+    x: f32, y: f32, radius: f32,
+    charge: f32, mass: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for CircleChargeConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        labelled_drag_value(ui, "radius:", &mut self.radius);
+        labelled_drag_value(ui, "charge:", &mut self.charge);
+        labelled_drag_value(ui, "mass:", &mut self.mass);
+
+        if self.mass < 0.1 { self.mass = 0.1; }
+        if self.radius < 0.5 { self.radius = 0.5; }
+        
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(CircleCharge {
+            charge: self.charge,
+            mass: self.mass,
+            radius: self.radius,
+            pos: Vec2::new(self.x, self.y),
+            vel: Vec2::ZERO
+        })
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.radius = 5.;
+        self.charge = 1.;
+        self.mass = 1.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct GlobalFieldConstructor {
+    e_x: f32, e_y: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for GlobalFieldConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "Ex:", &mut self.e_x);
+        labelled_drag_value(ui, "Ey:", &mut self.e_y);
+        
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(GlobalField {
+            field: Vec2::new(self.e_x, self.e_y)
+        })
+    }
+
+    fn reset(&mut self) {
+        self.e_x = 0.;
+        self.e_y = 0.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct GlobalMagneticFieldConstructor {
+    b_z: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for GlobalMagneticFieldConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "Bz:", &mut self.b_z);
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(GlobalMagneticField { field: self.b_z })
+    }
+
+    fn reset(&mut self) {
+        self.b_z = 0.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct LineChargeConstructor {
+    x1: f32, y1: f32, x2: f32, y2: f32,
+    charge: f32, mass: f32, fixed: bool
+}
+
+impl UiConstructor<Box<dyn Body>> for LineChargeConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x1:", &mut self.x1);
+        labelled_drag_value(ui, "y1:", &mut self.y1);
+        labelled_drag_value(ui, "x2:", &mut self.x2);
+        labelled_drag_value(ui, "y2:", &mut self.y2);
+        labelled_drag_value(ui, "charge:", &mut self.charge);
+        ui.checkbox(&mut self.fixed, "fixed");
+
+        if !self.fixed { labelled_drag_value(ui, "mass:", &mut self.mass); }
+        if self.mass < 0.1 { self.mass = 0.1; }
+        if self.x1 == self.x2 && self.y1 == self.y2 { self.x2 += 1.; }
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        let line = LineCharge::new(
+            Vec2::new(self.x1, self.y1), Vec2::new(self.x2, self.y2), self.charge
+        );
+
+        if self.fixed { Box::new(line) }
+        else { Box::new(line.with_motion(self.mass, Vec2::ZERO, 0.)) }
+    }
+
+    fn reset(&mut self) {
+        self.x1 = -50.;
+        self.y1 = 0.;
+        self.x2 = 50.;
+        self.y2 = 0.;
+        self.charge = 10.;
+        self.mass = 1.;
+        self.fixed = true;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct CurrentWireConstructor {
+    x: f32, y: f32,
+    mass: f32, current: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for CurrentWireConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        labelled_drag_value(ui, "current:", &mut self.current);
+        labelled_drag_value(ui, "mass:", &mut self.mass);
+
+        if self.mass < 0.1 { self.mass = 0.1; }
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        Box::new(CurrentWire {
+            current: self.current,
+            mass: self.mass,
+            pos: Vec2::new(self.x, self.y),
+            vel: Vec2::ZERO
+        })
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.mass = 1.;
+        self.current = 1.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct CurrentSheetConstructor {
+    x1: f32, y1: f32, x2: f32, y2: f32,
+    current: f32, mass: f32, fixed: bool
+}
+
+impl UiConstructor<Box<dyn Body>> for CurrentSheetConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x1:", &mut self.x1);
+        labelled_drag_value(ui, "y1:", &mut self.y1);
+        labelled_drag_value(ui, "x2:", &mut self.x2);
+        labelled_drag_value(ui, "y2:", &mut self.y2);
+        labelled_drag_value(ui, "current:", &mut self.current);
+        ui.checkbox(&mut self.fixed, "fixed");
+
+        if !self.fixed { labelled_drag_value(ui, "mass:", &mut self.mass); }
+        if self.mass < 0.1 { self.mass = 0.1; }
+        if self.x1 == self.x2 && self.y1 == self.y2 { self.x2 += 1.; }
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        let sheet = CurrentSheet::new(
+            Vec2::new(self.x1, self.y1), Vec2::new(self.x2, self.y2), self.current
+        );
+
+        if self.fixed { Box::new(sheet) }
+        else { Box::new(sheet.with_motion(self.mass, Vec2::ZERO, 0.)) }
+    }
+
+    fn reset(&mut self) {
+        self.x1 = -50.;
+        self.y1 = 0.;
+        self.x2 = 50.;
+        self.y2 = 0.;
+        self.current = 10.;
+        self.mass = 1.;
+        self.fixed = true;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct ConductorConstructor {
+    x: f32, y: f32,
+    rectangle: bool, radius: f32, width: f32, height: f32,
+    floating: bool, value: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for ConductorConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        ui.checkbox(&mut self.rectangle, "rectangle");
+
+        if self.rectangle {
+            labelled_drag_value(ui, "width:", &mut self.width);
+            labelled_drag_value(ui, "height:", &mut self.height);
+        }
+        else { labelled_drag_value(ui, "radius:", &mut self.radius); }
+
+        ui.checkbox(&mut self.floating, "floating");
+        let label = if self.floating { "charge:" } else { "potential:" };
+
+        labelled_drag_value(ui, label, &mut self.value);
+
+        if self.radius < 0.5 { self.radius = 0.5; }
+        if self.width < 0.5 { self.width = 0.5; }
+        if self.height < 0.5 { self.height = 0.5; }
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        let center = Vec2::new(self.x, self.y);
+        let outline = if self.rectangle {
+            let half = Vec2::new(self.width, self.height)/2.;
+
+            Outline::Polygon(vec![
+                center-half, center+Vec2::new(half.x, -half.y),
+                center+half, center+Vec2::new(-half.x, half.y)
+            ])
+        }
+        else { Outline::Circle { center: center, radius: self.radius } };
+        let condition = if self.floating { Condition::Floating(self.value) }
+                        else { Condition::Potential(self.value) };
+
+        Box::new(Conductor::new(vec![Electrode { outline, condition }], 64))
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.rectangle = false;
+        self.radius = 20.;
+        self.width = 100.;
+        self.height = 10.;
+        self.floating = false;
+        self.value = 0.;
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct DielectricConstructor {
+    x: f32, y: f32,
+    rectangle: bool, radius: f32, width: f32, height: f32,
+    permittivity: f32
+}
+
+impl UiConstructor<Box<dyn Body>> for DielectricConstructor {
+    fn make_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        labelled_drag_value(ui, "x:", &mut self.x);
+        labelled_drag_value(ui, "y:", &mut self.y);
+        ui.checkbox(&mut self.rectangle, "rectangle");
+
+        if self.rectangle {
+            labelled_drag_value(ui, "width:", &mut self.width);
+            labelled_drag_value(ui, "height:", &mut self.height);
+        }
+        else { labelled_drag_value(ui, "radius:", &mut self.radius); }
+
+        labelled_slider(ui, "permittivity:", &mut self.permittivity, 1.0..=20.0);
+
+        if self.radius < 0.5 { self.radius = 0.5; }
+        if self.width < 0.5 { self.width = 0.5; }
+        if self.height < 0.5 { self.height = 0.5; }
+
+        ui.button("OK").clicked()
+    }
+
+    fn get_value(&self) -> Box<dyn Body> {
+        let center = Vec2::new(self.x, self.y);
+        let outline = if self.rectangle {
+            let half = Vec2::new(self.width, self.height)/2.;
+
+            Outline::Polygon(vec![
+                center-half, center+Vec2::new(half.x, -half.y),
+                center+half, center+Vec2::new(-half.x, half.y)
+            ])
+        }
+        else { Outline::Circle { center: center, radius: self.radius } };
+
+        Box::new(Dielectric::new(vec![Region { outline, permittivity: self.permittivity }], 64))
+    }
+
+    fn reset(&mut self) {
+        self.x = 0.;
+        self.y = 0.;
+        self.rectangle = false;
+        self.radius = 40.;
+        self.width = 100.;
+        self.height = 40.;
+        self.permittivity = 4.;
+    }
+}
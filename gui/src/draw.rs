@@ -0,0 +1,42 @@
+use fieldsim::canvas::{Canvas, Color};
+use nannou::draw::Draw;
+use nannou::prelude::*;
+
+
+
+// Adapts a nannou Draw so that bodies can draw themselves onto it.
+pub struct NannouCanvas<'a>(pub &'a Draw);
+
+fn to_rgba(color: Color) -> Rgba {
+    rgba(color.r, color.g, color.b, color.a)
+}
+
+impl<'a> Canvas for NannouCanvas<'a> {
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: Color) {
+        self.0.ellipse()
+            .color(to_rgba(color))
+            .xy(center)
+            .radius(radius);
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: Color) {
+        self.0.line()
+            .start(start)
+            .end(end)
+            .stroke_weight(weight)
+            .color(to_rgba(color));
+    }
+
+    fn tri(&mut self, points: [Vec2; 3], color: Color) {
+        self.0.tri()
+            .color(to_rgba(color))
+            .points(points[0], points[1], points[2]);
+    }
+
+    fn polyline(&mut self, points: &[Vec2], weight: f32, color: Color) {
+        self.0.polyline()
+            .weight(weight)
+            .points(points.iter().copied())
+            .color(to_rgba(color));
+    }
+}
@@ -0,0 +1,1041 @@
+mod body_ui;
+mod draw;
+
+use std::collections::HashMap;
+use nannou::prelude::*;
+use nannou::{image, wgpu, winit};
+use nannou_egui::{egui, Egui};
+use nannou_egui::egui::plot::{Line, Plot, Value, Values};
+use fieldsim::bodies::*;
+use fieldsim::scene::{Scene, SceneError};
+use fieldsim::canvas::{draw_scene, Color, Viewport};
+use fieldsim::collision::Collisions;
+use fieldsim::conductor::Condition;
+use fieldsim::heatmap::{self, Colormap, HeatmapScale, HeatmapStyle};
+use fieldsim::images::{ImageBoundary, ImagedField, DRAWN_SAMPLES};
+use fieldsim::job::{LicJob, Seeding, TraceJob};
+use fieldsim::lic::{Lic, LicStyle, LicTint};
+use fieldsim::poisson::Grid;
+use fieldsim::quiver::{ArrowScaling, Quiver, QuiverStyle};
+use fieldsim::simulation::{self, Integrator, Simulator};
+use fieldsim::raster::{self, Background};
+use fieldsim::util::{self, LineEnd};
+use fieldsim::svg;
+use std::path::Path;
+use body_ui::*;
+use draw::NannouCanvas;
+
+
+
+// Flux between neighbouring field lines to begin with
+const FLUX_STEP: f32 = 10.;
+// Size in pixels of the squares the heat map is sampled on
+const HEATMAP_CELL: u32 = 6;
+// Pixels between the grid points the potential is sampled on to find isopotentials
+const CONTOUR_SPACING: f32 = 4.;
+// Shown when a body and an image boundary can't be had together
+const NOT_IMAGED: &str = "Only point, dipole, circle and line charges can be imaged in a boundary";
+
+fn main() {
+    nannou::app(model)
+           .loop_mode(LoopMode::RefreshSync)
+           .update(update)
+           .run();
+}
+
+enum State {
+    Simulating,
+    ShowGui,
+    AddIsopotential,
+    AddMagneticLine,
+    AddBody(String)
+}
+
+struct Model {
+    state: State,
+    bodies: Vec<Box<dyn Body>>,
+    isopotentials: Vec<Vec<Vec2>>,
+    field_lines: Vec<Vec<Vec2>>,
+    magnetic_lines: Vec<Vec<Vec2>>,
+    // Number of levels to find isopotentials at, or the potential between them if it is fixed
+    contour_levels: usize,
+    contour_step: Option<f32>,
+    // Flux between neighbouring field lines
+    flux_step: f32,
+    // Field lines being traced in the background, and whether they are traced again for a
+    // changed scene, in which case the lines drawn stay until the new ones are all finished
+    trace_job: Option<TraceJob>,
+    retracing: bool,
+    // How the heat map of the potential is coloured, if it is shown
+    heatmap_style: Option<HeatmapStyle>,
+    heatmap: Option<Heatmap>,
+    // How arrows showing the field are drawn, if they are shown, and the arrows themselves
+    quiver_style: Option<QuiverStyle>,
+    quiver: Option<Arrows>,
+    // How the LIC texture is drawn, if it is shown, the job computing it and the last one
+    // finished, along with a copy on the GPU
+    lic_style: Option<LicStyle>,
+    lic_job: Option<LicJob>,
+    lic: Option<(Lic, wgpu::Texture)>,
+    boundary_charge: Option<BoundaryCharge>,
+    simulator: Simulator,
+    constructors: HashMap<String, Box<dyn UiConstructor<Box<dyn Body>>>>,
+    selected_constructor: String,
+    scene_path: String,
+    status: String,
+    viewport: Viewport,
+    egui: Egui
+}
+
+// The charge induced on the image boundary, which takes thousands of field evaluations to
+// find, along with the scene it was found for.
+struct BoundaryCharge {
+    scene: Scene,
+    total: f32,
+    surface: Vec<(Vec2, f32)>,
+    // How far along the boundary each of the surface samples is
+    distances: Vec<f32>
+}
+
+// The heat map's samples over a coarse copy of the viewport and how they are coloured,
+// along with the scene they were taken from.
+struct Heatmap {
+    scene: Scene,
+    viewport: Viewport,
+    values: Vec<f32>,
+    scale: HeatmapScale
+}
+
+// Arrows showing the field, along with the scene, viewport and style they were made for.
+struct Arrows {
+    scene: Scene,
+    viewport: Viewport,
+    style: QuiverStyle,
+    quiver: Quiver
+}
+
+// Path given with --scene on the command line, if any.
+fn scene_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--scene" { return args.next(); }
+    }
+
+    None
+}
+
+fn load_scene(model: &mut Model, path: &str) -> Result<(), SceneError> {
+    let scene = Scene::load(path)?;
+
+    model.bodies = scene.bodies()?;
+    model.trace_job = None;
+    model.isopotentials = scene.isopotentials;
+    model.field_lines = scene.field_lines;
+    model.magnetic_lines = scene.magnetic_lines;
+    model.simulator = Simulator::new(scene.integrator).with_timestep(scene.timestep);
+    model.simulator.collisions = scene.collisions;
+    model.simulator.boundary = scene.boundary;
+    model.simulator.opening_angle = scene.opening_angle;
+
+    Ok(())
+}
+
+// The scene as it stands, without the traced field lines.
+fn current_scene(model: &Model) -> Scene {
+    let mut scene = Scene::new(&model.bodies, &model.isopotentials, &[]);
+
+    scene.integrator = model.simulator.integrator;
+    scene.timestep = model.simulator.timestep;
+    scene.collisions = model.simulator.collisions;
+    scene.boundary = model.simulator.boundary;
+    scene.opening_angle = model.simulator.opening_angle;
+    scene
+}
+
+fn save_scene(model: &Model, path: &str) -> Result<(), SceneError> {
+    let mut scene = current_scene(model);
+
+    scene.field_lines = model.field_lines.clone();
+    scene.magnetic_lines = model.magnetic_lines.clone();
+    scene.save(path)
+}
+
+// Start tracing field lines in the background, from every isopotential or straight from the
+// charges, replacing any lines already drawn.
+fn start_trace(model: &mut Model, seeding: Seeding) {
+    model.field_lines.clear();
+    model.trace_job = Some(TraceJob::start(current_scene(model), model.flux_step, seeding));
+    model.retracing = false;
+}
+
+// Add isopotentials across the whole window at evenly spaced levels, over the range the heat
+// map covers, with every piece of each level traced once.
+fn contour_window(model: &mut Model) {
+    let (lo, hi) = model.viewport.bounds();
+    let grid = Grid::covering(lo, hi, CONTOUR_SPACING/model.viewport.scale);
+    let field = ImagedField::new(&model.bodies, model.simulator.boundary.as_ref())
+                            .with_opening_angle(model.simulator.opening_angle);
+    let values = util::sample_grid(&field, &grid);
+    let scale = HeatmapScale::fit(&values, model.heatmap_style.unwrap_or_default());
+    let levels = match model.contour_step {
+        Some(step) => match scale.levels_every(step) {
+            Ok(levels) => levels,
+            Err(e) => {
+                model.status = format!("No isopotentials added: {}", e);
+                return;
+            }
+        },
+        None => scale.levels(model.contour_levels)
+    };
+    let lines = util::trace_isopotential_levels(&field, &values, &grid, &levels);
+
+    model.status = format!("Added {} isopotentials at {} levels", lines.len(), levels.len());
+    model.isopotentials.extend(lines);
+}
+
+// Show the lines traced in the background so far, tracing them again if the bodies or
+// isopotentials have changed since the trace began. As with the LIC texture, a running trace
+// is left to finish while the simulation runs, otherwise it would be restarted every frame
+// and never draw anything, and the last lines finished stay on screen in the meantime.
+fn poll_trace(model: &mut Model, scene: &Scene) {
+    if model.trace_job.is_none() { return; }
+
+    let simulating = matches!(model.state, State::Simulating);
+    let job = model.trace_job.as_mut().unwrap();
+    let changed = job.poll();
+
+    if (changed && !model.retracing) || job.is_finished() { model.field_lines = job.lines(); }
+
+    let stale = job.is_stale(scene);
+
+    if job.is_finished() && (!stale || job.error().is_some()) {
+        model.status = match job.error() {
+            Some(e) => e.to_string(),
+            None => describe_ends(&job.ends())
+        };
+        model.trace_job = None;
+    }
+    else if stale && (job.is_finished() || !simulating) {
+        let seeding = job.seeding();
+
+        model.trace_job = Some(TraceJob::start(scene.clone(), model.flux_step, seeding));
+        model.retracing = true;
+    }
+}
+
+// Say how many field line ends stopped for each reason.
+fn describe_ends(ends: &[[LineEnd; 2]]) -> String {
+    let count = |reason| ends.iter().flatten().filter(|e| **e == reason).count();
+
+    format!(
+        "Traced {} field lines: {} ends on charges, {} at the boundary, {} unfinished",
+        ends.len(), count(LineEnd::Charge), count(LineEnd::Boundary), count(LineEnd::MaxSteps)
+    )
+}
+
+fn model(app: &App) -> Model {
+    let window_id = app.new_window()
+                       .raw_event(raw_window_event)
+                       .key_pressed(key_pressed)
+                       .mouse_pressed(mouse_pressed)
+                       .view(view)
+                       .build()
+                       .unwrap();
+    
+    let window = app.window(window_id).unwrap();
+    let screen = app.window_rect();
+    let mut model = Model {
+        state: State::ShowGui,
+        bodies: Vec::new(),
+        isopotentials: Vec::new(),
+        field_lines: Vec::new(),
+        magnetic_lines: Vec::new(),
+        contour_levels: 10,
+        contour_step: None,
+        flux_step: FLUX_STEP,
+        trace_job: None,
+        retracing: false,
+        heatmap_style: None,
+        heatmap: None,
+        quiver_style: None,
+        quiver: None,
+        lic_style: None,
+        lic_job: None,
+        lic: None,
+        boundary_charge: None,
+        simulator: Simulator::default(),
+        constructors: HashMap::new(),
+        selected_constructor: String::new(),
+        scene_path: "scene.ron".to_string(),
+        status: String::new(),
+        viewport: Viewport::new(screen.w() as u32, screen.h() as u32),
+        egui: Egui::from_window(&window)
+    };
+    
+    model.constructors.insert(
+        "Point charge".to_string(), Box::new(PointChargeConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Dipole".to_string(), Box::new(DipoleConstructor::default())
+    );
+    
+    model.constructors.insert(
+        "Circle charge".to_string(), Box::new(CircleChargeConstructor::default())
+    );
+    
+    model.constructors.insert(
+        "Global field".to_string(), Box::new(GlobalFieldConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Global magnetic field".to_string(), Box::new(GlobalMagneticFieldConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Conductor".to_string(), Box::new(ConductorConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Dielectric".to_string(), Box::new(DielectricConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Line charge".to_string(), Box::new(LineChargeConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Current wire".to_string(), Box::new(CurrentWireConstructor::default())
+    );
+
+    model.constructors.insert(
+        "Current sheet".to_string(), Box::new(CurrentSheetConstructor::default())
+    );
+
+    model.selected_constructor = model.constructors.keys().nth(0).unwrap().clone();
+
+    if let Some(path) = scene_arg() {
+        if let Err(e) = load_scene(&mut model, &path) {
+            eprintln!("Could not load scene {}: {}", path, e);
+            std::process::exit(1);
+        }
+
+        model.scene_path = path;
+
+        return model;
+    }
+
+    model.bodies.push(Box::new(LineCharge::new(
+        Vec2::new(-100., 0.), Vec2::new(100., 0.), -100.
+    )));
+
+    model.bodies.push(Box::new(CircleCharge {
+        charge: 50.,
+        mass: 1.,
+        radius: 20.,
+        pos: Vec2::Y*50.,
+        vel: Vec2::ZERO
+    }));
+    
+    model.bodies.push(Box::new(CircleCharge {
+        charge: 50.,
+        mass: 1.,
+        radius: 20.,
+        pos: -Vec2::Y*50.,
+        vel: Vec2::ZERO
+    }));
+
+    model
+}
+
+fn raw_window_event(_app: &App, model: &mut Model, event: &winit::event::WindowEvent) {
+    if matches!(model.state, State::ShowGui | State::AddBody(_)) {
+        model.egui.handle_raw_event(event);
+    }
+}
+
+fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::Space => {
+            model.state = match model.state {
+                State::Simulating => State::ShowGui,
+                State::ShowGui => State::Simulating,
+                _ => { return; }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    let pos = Vec2::new(app.mouse.x, app.mouse.y);
+    let field = ImagedField::new(&model.bodies, model.simulator.boundary.as_ref())
+                            .with_opening_angle(model.simulator.opening_angle);
+
+    match button {
+        MouseButton::Left => {
+            if matches!(model.state, State::AddIsopotential) {
+                let points = util::trace_isopotential(&field, pos);
+
+                model.isopotentials.push(points);
+                model.state = State::ShowGui;
+            }
+            else if matches!(model.state, State::AddMagneticLine) {
+                let points = util::trace_isopotential(&VectorPotential(&field), pos);
+
+                model.magnetic_lines.push(points);
+                model.state = State::ShowGui;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn make_ui(model: &mut Model) {
+    let ctx = model.egui.begin_frame();
+
+    egui::Window::new("Menu").show(&ctx, |ui| {
+        if ui.button("Add isopotential").clicked() {
+            model.state = State::AddIsopotential;
+        }
+
+        if ui.button("Draw field lines").clicked() { start_trace(model, Seeding::Isopotentials); }
+
+        let mut auto = false;
+
+        ui.horizontal(|ui| {
+            auto = ui.button("Auto field lines").clicked();
+            ui.add(egui::Slider::new(&mut model.flux_step, 1.0..=100.0)
+                       .logarithmic(true)
+                       .text("Flux per line"));
+        });
+
+        if auto { start_trace(model, Seeding::Charges); }
+
+        let mut cancel = false;
+
+        if let Some(job) = &model.trace_job {
+            ui.horizontal(|ui| {
+                ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+                cancel = ui.button("Cancel").clicked();
+            });
+        }
+
+        if cancel { model.trace_job = None; }
+
+        let mut contour = false;
+
+        ui.horizontal(|ui| {
+            contour = ui.button("Contour isopotentials").clicked();
+
+            match &mut model.contour_step {
+                Some(step) => {
+                    ui.add(egui::DragValue::new(step).speed(0.1).clamp_range(0.01..=1000.0));
+                    ui.label("ΔV");
+                }
+                None => {
+                    ui.add(egui::Slider::new(&mut model.contour_levels, 1..=40).text("Levels"));
+                }
+            }
+
+            let mut fixed = model.contour_step.is_some();
+
+            if ui.checkbox(&mut fixed, "Fixed ΔV").changed() {
+                model.contour_step = if fixed { Some(1.) } else { None };
+            }
+        });
+
+        if contour { contour_window(model); }
+
+        if ui.button("Add magnetic field line").clicked() {
+            model.state = State::AddMagneticLine;
+        }
+
+        if ui.button("Clear lines").clicked() {
+            model.trace_job = None;
+            model.isopotentials.clear();
+            model.field_lines.clear();
+            model.magnetic_lines.clear();
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut model.scene_path);
+
+            if ui.button("Save").clicked() {
+                model.status = match save_scene(model, &model.scene_path) {
+                    Ok(()) => format!("Saved {}", model.scene_path),
+                    Err(e) => e.to_string()
+                };
+            }
+
+            if ui.button("Load").clicked() {
+                let path = model.scene_path.clone();
+
+                model.status = match load_scene(model, &path) {
+                    Ok(()) => format!("Loaded {}", path),
+                    Err(e) => e.to_string()
+                };
+            }
+        });
+
+        if ui.button("Export SVG").clicked() {
+            let path = Path::new(&model.scene_path).with_extension("svg");
+            let image = svg::render_svg(
+                model.viewport, &model.bodies, model.simulator.boundary.as_ref(),
+                &model.isopotentials, &model.field_lines,
+                &model.magnetic_lines, model.quiver.as_ref().map(|a| &a.quiver)
+            );
+
+            model.status = match std::fs::write(&path, image) {
+                Ok(()) => format!("Exported {}", path.display()),
+                Err(e) => format!("Could not export {}: {}", path.display(), e)
+            };
+        }
+
+        if ui.button("Export PNG").clicked() {
+            let path = Path::new(&model.scene_path).with_extension("png");
+            let field = ImagedField::new(&model.bodies, model.simulator.boundary.as_ref())
+                                    .with_opening_angle(model.simulator.opening_angle);
+            let fits = |lic: &Lic| {
+                (lic.width(), lic.height()) == (model.viewport.width, model.viewport.height)
+            };
+            let background = match (&model.lic, model.heatmap_style) {
+                (Some((lic, _)), _) if fits(lic) => Background::Lic(lic),
+                (_, Some(style)) => Background::Heatmap(&field, style),
+                _ => Background::Black
+            };
+            let image = raster::render_raster(
+                model.viewport, &model.bodies, model.simulator.boundary.as_ref(),
+                &model.isopotentials, &model.field_lines,
+                &model.magnetic_lines, background, model.quiver.as_ref().map(|a| &a.quiver)
+            );
+
+            model.status = match image.map(|i| i.save_png(&path)) {
+                Some(Ok(())) => format!("Exported {}", path.display()),
+                Some(Err(e)) => format!("Could not export {}: {}", path.display(), e),
+                None => "Window is too large to export".to_string()
+            };
+        }
+
+        if !model.status.is_empty() { ui.label(&model.status); }
+        
+        let integrator = &mut model.simulator.integrator;
+
+        egui::ComboBox::from_label("Integrator")
+            .selected_text(integrator.name())
+            .show_ui(ui, |ui| {
+                for option in Integrator::ALL {
+                    ui.selectable_value(integrator, option, option.name());
+                }
+            });
+
+        if let Integrator::Rk45 { tolerance } = integrator {
+            ui.horizontal(|ui| {
+                ui.label("Tolerance:");
+                ui.add(egui::DragValue::new(tolerance).speed(1e-4).clamp_range(1e-6..=1.));
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Timestep:");
+            ui.add(
+                egui::DragValue::new(&mut model.simulator.timestep)
+                    .speed(1e-4)
+                    .clamp_range(1e-5..=0.1)
+            );
+        });
+
+        ui.add(egui::Slider::new(&mut model.simulator.speed, 0.0..=10.0).text("Speed"));
+
+        let mut collide = model.simulator.collisions.is_some();
+
+        ui.checkbox(&mut collide, "Collisions");
+
+        match (collide, &mut model.simulator.collisions) {
+            (true, Some(c)) => {
+                ui.add(egui::Slider::new(&mut c.restitution, 0.0..=1.0).text("Restitution"));
+                ui.checkbox(&mut c.merge, "Merge on contact");
+            }
+            (true, c) => *c = Some(Collisions::default()),
+            (false, c) => *c = None
+        }
+
+        let boundary = model.simulator.boundary;
+
+        boundary_ui(ui, &mut model.simulator.boundary, model.boundary_charge.as_ref());
+
+        // A boundary can't be chosen while there are bodies it can't image
+        if boundary.is_none() && model.simulator.boundary.is_some()
+           && model.bodies.iter().any(|b| b.image_sources().is_none()) {
+            model.simulator.boundary = None;
+            model.status = NOT_IMAGED.to_string();
+        }
+        let (heatmap_shown, lic_shown) = (model.heatmap_style.is_some(), model.lic_style.is_some());
+
+        heatmap_ui(ui, &mut model.heatmap_style, model.heatmap.as_ref().map(|h| &h.scale));
+        quiver_ui(ui, &mut model.quiver_style);
+
+        let job = model.lic_job.as_ref().filter(|j| !j.is_finished());
+
+        lic_ui(ui, &mut model.lic_style, job.map(|j| j.progress()));
+
+        // The LIC texture covers the whole window, which would hide the heat map, so turning
+        // either on turns the other off
+        if model.heatmap_style.is_some() && !heatmap_shown { model.lic_style = None; }
+        if model.lic_style.is_some() && !lic_shown { model.heatmap_style = None; }
+
+        let mut tree = model.simulator.opening_angle.is_some();
+
+        ui.checkbox(&mut tree, "Barnes-Hut tree");
+
+        match (tree, &mut model.simulator.opening_angle) {
+            (true, Some(theta)) => {
+                ui.add(egui::Slider::new(theta, 0.1..=0.9).text("Opening angle"));
+            }
+            (true, theta) => *theta = Some(0.5),
+            (false, theta) => *theta = None
+        }
+
+        ui.label(format!(
+            "Step {} (t = {:.3})", model.simulator.steps(), model.simulator.time()
+        ));
+
+        ui.horizontal(|ui| {
+            let selected = &mut model.selected_constructor;
+
+            egui::ComboBox::from_label("")
+                .selected_text(selected.clone())
+                .show_ui(ui, |ui| {
+                    for key in model.constructors.keys() {
+                        ui.selectable_value(selected, key.to_string(), key.to_string());
+                    }
+                });
+
+            if ui.button("Add").clicked() {
+                model.state = State::AddBody(selected.clone());
+                model.constructors.get_mut(selected).unwrap().reset();
+            }
+        });
+    });
+}
+
+// Choose an image boundary from a few presets, then adjust it.
+fn boundary_ui(
+    ui: &mut egui::Ui,
+    boundary: &mut Option<ImageBoundary>,
+    charge: Option<&BoundaryCharge>
+) {
+    let name = |b: &Option<ImageBoundary>| match b {
+        None => "None",
+        Some(ImageBoundary::Line { .. }) => "Grounded line",
+        Some(ImageBoundary::Circle { condition: Condition::Potential(_), .. }) => "Held circle",
+        Some(ImageBoundary::Circle { condition: Condition::Floating(_), .. }) => "Floating circle"
+    };
+    let presets = [
+        None,
+        Some(ImageBoundary::Line { point: Vec2::new(0., -200.), normal: Vec2::Y }),
+        Some(ImageBoundary::Circle {
+            center: Vec2::ZERO, radius: 100., condition: Condition::Potential(0.)
+        }),
+        Some(ImageBoundary::Circle {
+            center: Vec2::ZERO, radius: 100., condition: Condition::Floating(0.)
+        })
+    ];
+    let mut selected = name(boundary);
+
+    egui::ComboBox::from_label("Boundary")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for option in presets {
+                ui.selectable_value(&mut selected, name(&option), name(&option));
+            }
+        });
+
+    if selected != name(boundary) {
+        *boundary = presets.into_iter().find(|p| name(p) == selected).unwrap();
+    }
+
+    match boundary {
+        Some(ImageBoundary::Line { point, .. }) => {
+            ui.horizontal(|ui| {
+                ui.label("Height:");
+                ui.add(egui::DragValue::new(&mut point.y));
+            });
+        }
+        Some(ImageBoundary::Circle { radius, condition, .. }) => {
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(egui::DragValue::new(radius).clamp_range(1.1..=1000.));
+
+                match condition {
+                    Condition::Potential(v) => {
+                        ui.label("Potential:");
+                        ui.add(egui::DragValue::new(v));
+                    }
+                    Condition::Floating(q) => {
+                        ui.label("Charge:");
+                        ui.add(egui::DragValue::new(q));
+                    }
+                }
+            });
+        }
+        None => {}
+    }
+
+    // The charge per unit length along the boundary, plotted against distance along it
+    if let (Some(_), Some(charge)) = (boundary, charge) {
+        let points = charge.distances
+                           .iter()
+                           .zip(&charge.surface)
+                           .map(|(s, (_, q))| Value::new(*s, *q));
+
+        ui.label(format!("Induced charge: {:.2}", charge.total));
+        Plot::new("surface_charge")
+            .height(80.)
+            .show(ui, |plot| plot.line(Line::new(Values::from_values_iter(points))));
+    }
+}
+
+// Turn the heat map on and off and choose how it is coloured, with a colour bar for the
+// current scale.
+fn heatmap_ui(ui: &mut egui::Ui, style: &mut Option<HeatmapStyle>, scale: Option<&HeatmapScale>) {
+    let mut shown = style.is_some();
+
+    ui.checkbox(&mut shown, "Heat map");
+
+    let style = match (shown, style) {
+        (true, Some(s)) => s,
+        (true, s) => s.insert(HeatmapStyle::default()),
+        (false, s) => {
+            *s = None;
+            return;
+        }
+    };
+
+    egui::ComboBox::from_label("Colour map")
+        .selected_text(style.colormap.name())
+        .show_ui(ui, |ui| {
+            for option in Colormap::ALL {
+                ui.selectable_value(&mut style.colormap, option, option.name());
+            }
+        });
+
+    ui.horizontal(|ui| {
+        let mut banded = style.bands.is_some();
+
+        ui.checkbox(&mut banded, "Bands");
+
+        match (banded, &mut style.bands) {
+            (true, Some(n)) => { ui.add(egui::Slider::new(n, 2..=40)); }
+            (true, n) => *n = Some(10),
+            (false, n) => *n = None
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let mut fixed = style.reference.is_some();
+
+        ui.checkbox(&mut fixed, "Fixed reference");
+
+        match (fixed, &mut style.reference) {
+            (true, Some(v)) => { ui.add(egui::DragValue::new(v)); }
+            (true, v) => *v = Some(scale.map_or(0., |s| s.reference)),
+            (false, v) => *v = None
+        }
+    });
+
+    if let Some(scale) = scale {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(200., 12.), egui::Sense::hover());
+        let legend = scale.legend(100);
+        let width = rect.width()/legend.len() as f32;
+
+        for (i, (_, c)) in legend.iter().enumerate() {
+            let left = rect.left()+width*i as f32;
+            let cell = egui::Rect::from_min_max(
+                egui::pos2(left, rect.top()), egui::pos2(left+width, rect.bottom())
+            );
+            let to_u8 = |x: f32| (x*255.).round() as u8;
+
+            ui.painter().rect_filled(cell, 0., egui::Color32::from_rgb(
+                to_u8(c.r), to_u8(c.g), to_u8(c.b)
+            ));
+        }
+
+        ui.columns(3, |columns| {
+            columns[0].label(format!("{:.1}", scale.reference-scale.range));
+            columns[1].label(format!("{:.1}", scale.reference));
+            columns[2].label(format!("{:.1}", scale.reference+scale.range));
+        });
+    }
+}
+
+// Turn the arrows showing the field on and off and choose how they are drawn.
+fn quiver_ui(ui: &mut egui::Ui, style: &mut Option<QuiverStyle>) {
+    let mut shown = style.is_some();
+
+    ui.checkbox(&mut shown, "Arrows");
+
+    let style = match (shown, style) {
+        (true, Some(s)) => s,
+        (true, s) => s.insert(QuiverStyle::default()),
+        (false, s) => {
+            *s = None;
+            return;
+        }
+    };
+
+    ui.add(egui::Slider::new(&mut style.spacing, 10.0..=100.0).text("Spacing"));
+
+    egui::ComboBox::from_label("Arrow length")
+        .selected_text(style.scaling.name())
+        .show_ui(ui, |ui| {
+            for option in ArrowScaling::ALL {
+                ui.selectable_value(&mut style.scaling, option, option.name());
+            }
+        });
+
+    ui.horizontal(|ui| {
+        let mut rgb = [style.color.r, style.color.g, style.color.b];
+
+        ui.checkbox(&mut style.jitter, "Jitter");
+        ui.label("Colour:");
+        ui.color_edit_button_rgb(&mut rgb);
+        style.color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+    });
+}
+
+// Turn the LIC texture on and off and choose how it is drawn, with its progress while it is
+// being computed.
+fn lic_ui(ui: &mut egui::Ui, style: &mut Option<LicStyle>, progress: Option<f32>) {
+    let mut shown = style.is_some();
+
+    ui.checkbox(&mut shown, "Line integral convolution");
+
+    let style = match (shown, style) {
+        (true, Some(s)) => s,
+        (true, s) => s.insert(LicStyle::default()),
+        (false, s) => {
+            *s = None;
+            return;
+        }
+    };
+
+    ui.add(egui::Slider::new(&mut style.length, 5.0..=50.0).text("Streak length"));
+
+    egui::ComboBox::from_label("Tint")
+        .selected_text(style.tint.name())
+        .show_ui(ui, |ui| {
+            for option in LicTint::ALL {
+                ui.selectable_value(&mut style.tint, option, option.name());
+            }
+        });
+
+    if let Some(p) = progress { ui.add(egui::ProgressBar::new(p).show_percentage()); }
+}
+
+// Resample the heat map, if it is shown, whenever the field, window or style changes, so
+// that it follows the bodies as they move but costs nothing while they are still.
+fn update_heatmap(model: &mut Model, scene: &Scene) {
+    let style = match model.heatmap_style {
+        Some(style) => style,
+        None => {
+            model.heatmap = None;
+            return;
+        }
+    };
+    let viewport = model.viewport.coarsened(HEATMAP_CELL);
+    let fresh = model.heatmap.as_ref().is_some_and(|h| {
+        h.scene.same_field(scene) && h.viewport == viewport && h.scale.style == style
+    });
+
+    if fresh { return; }
+
+    let field = ImagedField::new(&model.bodies, model.simulator.boundary.as_ref())
+                            .with_opening_angle(model.simulator.opening_angle);
+    let values = heatmap::sample_potential(&field, &viewport);
+    let scale = HeatmapScale::fit(&values, style);
+
+    model.heatmap = Some(Heatmap {
+        scene: scene.clone(),
+        viewport: viewport,
+        values: values,
+        scale: scale
+    });
+}
+
+// Resample the arrows, if they are shown, whenever the field, window or style changes, as
+// for the heat map.
+fn update_quiver(model: &mut Model, scene: &Scene) {
+    let style = match model.quiver_style {
+        Some(style) => style,
+        None => {
+            model.quiver = None;
+            return;
+        }
+    };
+    let fresh = model.quiver.as_ref().is_some_and(|a| {
+        a.scene.same_field(scene) && a.viewport == model.viewport && a.style == style
+    });
+
+    if fresh { return; }
+
+    let field = ImagedField::new(&model.bodies, model.simulator.boundary.as_ref())
+                            .with_opening_angle(model.simulator.opening_angle);
+
+    model.quiver = Some(Arrows {
+        quiver: Quiver::new(&field, &model.viewport, style),
+        scene: scene.clone(),
+        viewport: model.viewport,
+        style: style
+    });
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    let screen = app.window_rect();
+
+    model.egui.set_elapsed_time(update.since_start);
+    model.viewport = Viewport::new(screen.w() as u32, screen.h() as u32);
+
+    let dt = update.since_last.as_secs_f32();
+    let mut next_state = None;
+
+    match model.state {
+        State::Simulating => { model.simulator.advance(&mut model.bodies, dt); }
+        State::ShowGui => make_ui(model),
+        State::AddBody(ref name) => {
+            let b = model.constructors.get_mut(name).unwrap();
+            let ctx = model.egui.begin_frame();
+            
+            egui::Window::new("Add").show(&ctx, |ui| {
+                if b.make_ui(ui) {
+                    let body = b.get_value();
+
+                    next_state = Some(State::ShowGui);
+
+                    // Bodies the boundary can't image aren't added alongside it
+                    if model.simulator.boundary.is_some() && body.image_sources().is_none() {
+                        model.status = NOT_IMAGED.to_string();
+                    }
+                    else {
+                        model.bodies.push(body);
+                        simulation::induce_charges(&mut model.bodies);
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+
+    if let Some(s) = next_state { model.state = s; }
+
+    // Everything which follows the field is checked against the same copy of the scene,
+    // built once a frame
+    let scene = current_scene(model);
+
+    poll_trace(model, &scene);
+    update_boundary_charge(model, &scene);
+    update_heatmap(model, &scene);
+    update_quiver(model, &scene);
+    update_lic(app, model, &scene);
+}
+
+// Find the charge induced on the boundary again, but only once the field has changed.
+fn update_boundary_charge(model: &mut Model, scene: &Scene) {
+    let boundary = match model.simulator.boundary {
+        Some(b) => b,
+        None => {
+            model.boundary_charge = None;
+            return;
+        }
+    };
+    if model.boundary_charge.as_ref().is_some_and(|c| c.scene.same_field(scene)) { return; }
+
+    model.boundary_charge = Some(BoundaryCharge {
+        total: boundary.induced_charge(&model.bodies),
+        surface: boundary.surface_charge(&model.bodies, DRAWN_SAMPLES),
+        distances: boundary.sample_distances(DRAWN_SAMPLES),
+        scene: scene.clone()
+    });
+}
+
+// Keep the LIC texture, if it is shown, up to date with the field and the window. It is
+// recomputed in the background whenever either changes, and the last one finished stays on
+// screen in the meantime. A job is left to finish even once it is stale, otherwise a running
+// simulation would restart it every frame and the texture would never catch up.
+fn update_lic(app: &App, model: &mut Model, scene: &Scene) {
+    let style = match model.lic_style {
+        Some(style) => style,
+        None => {
+            model.lic_job = None;
+            model.lic = None;
+            return;
+        }
+    };
+    let stale = match &model.lic_job {
+        Some(job) => job.is_finished() && job.is_stale(scene, &model.viewport, style),
+        None => true
+    };
+
+    if stale { model.lic_job = Some(LicJob::start(scene.clone(), model.viewport, style)); }
+
+    match model.lic_job.as_mut().unwrap().poll() {
+        Some(Ok(lic)) => {
+            let pixels = image::RgbaImage::from_raw(lic.width(), lic.height(), lic.to_rgba8());
+            let image = image::DynamicImage::ImageRgba8(pixels.unwrap());
+
+            model.lic = Some((lic, wgpu::Texture::from_image(app, &image)));
+        }
+        Some(Err(e)) => model.status = e,
+        None => {}
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let screen = app.window_rect();
+    let draw = app.draw();
+    
+    let mut canvas = NannouCanvas(&draw);
+
+    draw.background().color(BLACK);
+
+    if let Some(h) = &model.heatmap {
+        heatmap::draw_heatmap(&mut canvas, &h.viewport, &h.values, &h.scale);
+    }
+
+    if let Some((_, texture)) = &model.lic {
+        draw.texture(texture).w_h(screen.w(), screen.h());
+    }
+
+    if let Some(a) = &model.quiver { a.quiver.draw(&mut canvas); }
+
+    // The boundary is drawn with the charge already found for it, rather than by draw_scene
+    if let (Some(b), Some(charge)) = (&model.simulator.boundary, &model.boundary_charge) {
+        b.draw(&mut canvas, &charge.surface);
+    }
+
+    draw_scene(
+        &mut canvas, &model.bodies, None, &model.isopotentials, &model.field_lines,
+        &model.magnetic_lines
+    );
+    
+    draw.text(match model.state {
+            State::Simulating => "Running",
+            State::ShowGui => "Paused",
+            State::AddIsopotential => "Adding isopotential",
+            State::AddMagneticLine => "Adding magnetic field line",
+            State::AddBody(_) => "Adding body"
+        })
+        .x_y((screen.left()+screen.right())/2., screen.top()-10.);
+    
+    if let State::AddBody(ref b) = model.state {
+        model.constructors[b].get_value().draw(&mut canvas);
+    }
+
+    draw.to_frame(app, &frame).unwrap();
+    
+    if matches!(model.state, State::ShowGui | State::AddBody(_)) {
+        model.egui.draw_to_frame(&frame).unwrap();
+    }
+}
+
@@ -0,0 +1,286 @@
+// Headless renderer. Loads a scene, retraces its isopotentials and magnetic field lines from
+// their starting points, traces the field lines crossing the isopotentials, or from the
+// charges, and writes the result to an SVG or PNG file, and if asked the charge induced on
+// the boundary to a CSV file.
+
+use fieldsim::bodies::{Body, VectorPotential};
+use fieldsim::canvas::Viewport;
+use fieldsim::heatmap::{Colormap, HeatmapScale, HeatmapStyle};
+use fieldsim::poisson::Grid;
+use fieldsim::quiver::{ArrowScaling, Quiver, QuiverStyle};
+use fieldsim::images::{ImageBoundary, ImagedField, DRAWN_SAMPLES};
+use fieldsim::lic::{Lic, LicStyle, LicTint};
+use fieldsim::scene::Scene;
+use fieldsim::raster::{self, Background};
+use fieldsim::{svg, util, Vec2};
+use std::process;
+
+const USAGE: &str = "\
+usage: fieldsim-render <scene.ron> <output.svg|output.png> [options]
+
+options:
+    --size <W>x<H>     output size in pixels (default 1024x768)
+    --scale <S>        pixels per world unit (default 1)
+    --center <X>,<Y>   world point at the centre of the image (default 0,0)
+    --flux <F>         flux between neighbouring field lines (default 10)
+    --from-charges     start field lines from the charges rather than the isopotentials
+    --contours <N>     also trace isopotentials across the image at N evenly spaced levels
+    --contour-step <V> or at every multiple of V instead
+    --heatmap          draw a heat map of the potential behind a PNG
+    --colormap <NAME>  heat map colours: diverging, sequential or magnitude (default diverging)
+    --bands <N>        fill the heat map in N flat bands rather than shading smoothly
+    --reference <V>    potential at the middle of the heat map (default the median)
+    --arrows <P>       draw arrows showing the field, P pixels apart
+    --lengths <S>      arrow lengths: linear, log or normalised (default log)
+    --jitter           move the arrows off their grid by a random amount
+    --lic              draw a line integral convolution texture of the field behind a PNG
+    --tint <T>         colour the texture: none, strength or potential (default none)
+    --surface-charge <FILE>
+                       also write the charge induced along the boundary to a CSV file
+
+--colormap, --bands and --reference imply --heatmap, --lengths and --jitter imply arrows
+30 pixels apart, and --tint implies --lic. --heatmap and --lic can't be used together.";
+
+struct Options {
+    scene: String,
+    output: String,
+    viewport: Viewport,
+    flux_step: f32,
+    from_charges: bool,
+    contours: usize,
+    contour_step: Option<f32>,
+    heatmap: Option<HeatmapStyle>,
+    quiver: Option<QuiverStyle>,
+    lic: Option<LicStyle>,
+    surface_charge: Option<String>
+}
+
+fn parse_pair<T: std::str::FromStr>(s: &str, sep: char) -> Option<(T, T)> {
+    let (a, b) = s.split_once(sep)?;
+
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut positional = Vec::new();
+    let mut viewport = Viewport::new(1024, 768);
+    let mut flux_step = 10.;
+    let mut from_charges = false;
+    let mut contours = 0;
+    let mut contour_step = None;
+    let mut heatmap: Option<HeatmapStyle> = None;
+    let mut quiver: Option<QuiverStyle> = None;
+    let mut lic: Option<LicStyle> = None;
+    let mut surface_charge = None;
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+
+        if arg == "--heatmap" {
+            heatmap.get_or_insert_with(HeatmapStyle::default);
+            continue;
+        }
+
+        if arg == "--lic" {
+            lic.get_or_insert_with(LicStyle::default);
+            continue;
+        }
+
+        if arg == "--from-charges" {
+            from_charges = true;
+            continue;
+        }
+
+        if arg == "--jitter" {
+            quiver.get_or_insert_with(QuiverStyle::default).jitter = true;
+            continue;
+        }
+
+        let value = args.next().ok_or(format!("{} needs a value", arg))?;
+        let bad_value = || format!("bad value for {}: {}", arg, value);
+
+        match arg.as_str() {
+            "--size" => {
+                let (w, h) = parse_pair(&value, 'x').ok_or_else(bad_value)?;
+
+                viewport.width = w;
+                viewport.height = h;
+            }
+            "--scale" => viewport.scale = value.parse().map_err(|_| bad_value())?,
+            "--center" => {
+                let (x, y) = parse_pair(&value, ',').ok_or_else(bad_value)?;
+
+                viewport.center = Vec2::new(x, y);
+            }
+            "--flux" => flux_step = value.parse().map_err(|_| bad_value())?,
+            "--contours" => contours = value.parse().map_err(|_| bad_value())?,
+            "--contour-step" => {
+                let step = value.parse().ok().filter(|v: &f32| *v > 0. && v.is_finite());
+
+                contour_step = Some(step.ok_or_else(bad_value)?);
+            }
+            "--arrows" => {
+                let spacing = value.parse().ok().filter(|p: &f32| *p >= 1.);
+
+                quiver.get_or_insert_with(QuiverStyle::default).spacing =
+                    spacing.ok_or_else(bad_value)?;
+            }
+            "--tint" => {
+                lic.get_or_insert_with(LicStyle::default).tint = match value.as_str() {
+                    "none" => LicTint::None,
+                    "strength" => LicTint::Strength,
+                    "potential" => LicTint::Potential,
+                    _ => return Err(bad_value())
+                };
+            }
+            "--lengths" => {
+                quiver.get_or_insert_with(QuiverStyle::default).scaling = match value.as_str() {
+                    "linear" => ArrowScaling::Linear,
+                    "log" => ArrowScaling::Log,
+                    "normalised" => ArrowScaling::Normalised,
+                    _ => return Err(bad_value())
+                };
+            }
+            "--colormap" => {
+                heatmap.get_or_insert_with(HeatmapStyle::default).colormap = match value.as_str() {
+                    "diverging" => Colormap::Diverging,
+                    "sequential" => Colormap::Sequential,
+                    "magnitude" => Colormap::LogMagnitude,
+                    _ => return Err(bad_value())
+                };
+            }
+            "--bands" => {
+                let bands = value.parse().ok().filter(|n| *n > 0).ok_or_else(bad_value)?;
+
+                heatmap.get_or_insert_with(HeatmapStyle::default).bands = Some(bands);
+            }
+            "--reference" => {
+                let reference = value.parse().ok().filter(|v: &f32| v.is_finite());
+
+                heatmap.get_or_insert_with(HeatmapStyle::default).reference =
+                    Some(reference.ok_or_else(bad_value)?);
+            }
+            "--surface-charge" => surface_charge = Some(value),
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+
+    if viewport.width == 0 || viewport.height == 0 || viewport.scale <= 0. || flux_step <= 0. {
+        return Err("size, scale and flux must be positive".to_string());
+    }
+
+    if heatmap.is_some() && lic.is_some() {
+        return Err("choose either a heat map or a LIC texture".to_string());
+    }
+
+    match <[String; 2]>::try_from(positional) {
+        Ok([scene, output]) => {
+            Ok(Options {
+                scene, output, viewport, flux_step, from_charges, contours, contour_step,
+                heatmap, quiver, lic, surface_charge
+            })
+        }
+        Err(_) => Err("expected a scene file and an output file".to_string())
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let scene = Scene::load(&options.scene).map_err(|e| format!("{}: {}", options.scene, e))?;
+    let bodies = scene.bodies().map_err(|e| e.to_string())?;
+    let boundary = scene.boundary.as_ref();
+    let field = ImagedField::new(&bodies, boundary).with_opening_angle(scene.opening_angle);
+
+    if let Some(path) = &options.surface_charge {
+        let boundary = boundary.ok_or("--surface-charge needs a scene with a boundary")?;
+
+        write_surface_charge(boundary, &bodies, path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    let starts = |lines: &[Vec<Vec2>]| -> Vec<Vec2> {
+        lines.iter().filter_map(|l| l.first().copied()).collect()
+    };
+    let mut isopotentials = util::trace_isopotentials(&field, &starts(&scene.isopotentials));
+
+    // Find the isopotentials' pieces on a grid of one point every four pixels
+    if options.contours > 0 || options.contour_step.is_some() {
+        let (lo, hi) = options.viewport.bounds();
+        let grid = Grid::covering(lo, hi, 4./options.viewport.scale);
+        let values = util::sample_grid(&field, &grid);
+        let scale = HeatmapScale::fit(&values, options.heatmap.unwrap_or_default());
+        let levels = match options.contour_step {
+            Some(step) => scale.levels_every(step).map_err(|e| format!("--contour-step: {}", e))?,
+            None => scale.levels(options.contours)
+        };
+
+        isopotentials.extend(util::trace_isopotential_levels(&field, &values, &grid, &levels));
+    }
+
+    let field_lines = if options.from_charges {
+        util::auto_field_lines(&field, &bodies, options.flux_step)
+    }
+    else { util::trace_field_lines(&field, &bodies, &isopotentials, options.flux_step) };
+    let magnetic_lines = util::trace_isopotentials(
+        &VectorPotential(&field), &starts(&scene.magnetic_lines)
+    );
+    let quiver = options.quiver.map(|style| Quiver::new(&field, &options.viewport, style));
+
+    if options.output.ends_with(".svg") {
+        let image = svg::render_svg(
+            options.viewport, &bodies, boundary, &isopotentials, &field_lines, &magnetic_lines,
+            quiver.as_ref()
+        );
+
+        std::fs::write(&options.output, image).map_err(|e| format!("{}: {}", options.output, e))
+    }
+    else if options.output.ends_with(".png") {
+        let lic = options.lic.map(|style| Lic::new(&field, &options.viewport, style));
+        let background = match (&lic, options.heatmap) {
+            (Some(lic), _) => Background::Lic(lic),
+            (None, Some(style)) => Background::Heatmap(&field, style),
+            (None, None) => Background::Black
+        };
+        let image = raster::render_raster(
+            options.viewport, &bodies, boundary, &isopotentials, &field_lines, &magnetic_lines,
+            background, quiver.as_ref()
+        ).ok_or("image size is too large")?;
+
+        image.save_png(&options.output).map_err(|e| format!("{}: {}", options.output, e))
+    }
+    else {
+        Err(format!("{}: unsupported output format", options.output))
+    }
+}
+
+// Write the charge per unit length induced at points along the boundary, with their
+// distances along it, as the GUI plots it.
+fn write_surface_charge(
+    boundary: &ImageBoundary,
+    bodies: &[Box<dyn Body>],
+    path: &str
+) -> std::io::Result<()> {
+    let charge = boundary.surface_charge(bodies, DRAWN_SAMPLES);
+    let distances = boundary.sample_distances(DRAWN_SAMPLES);
+    let mut csv = "distance,x,y,charge\n".to_string();
+
+    for (s, (p, q)) in distances.iter().zip(charge) {
+        csv += &format!("{},{},{},{}\n", s, p.x, p.y, q);
+    }
+
+    std::fs::write(path, csv)
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -1,18 +1,177 @@
-use nannou::draw::Draw;
-use nannou::prelude::*;
+use glam::{Mat2, Vec2, Vec3};
 use std::ops::DerefMut;
+use crate::canvas::*;
+use crate::collision::Shape;
+use crate::images::ImageSource;
+use crate::scene::SceneBody;
 
 
 
-pub trait Body {
-    fn pos(&self) -> Vec2;
+// Anything that produces an electric field. Every body is one, and so is any collection of
+// bodies, such as the rest of the world as seen by one body during the simulation. Fields
+// are shared between threads to trace many lines at once.
+pub trait Field: Send + Sync {
     fn e_field(&self, pos: Vec2) -> Vec2;
     fn potential(&self, pos: Vec2) -> f32;
-    fn update(&mut self, e_field: Vec2, dt: f32);
-    fn draw(&self, draw: &Draw);
+
+    // Magnetic field and the out-of-plane component of the vector potential, A_z, kept apart
+    // from the electric field. Currents through the plane give an in-plane field, which
+    // follows the contours of A_z. Bodies carrying no current leave these at zero.
+    fn b_field(&self, _pos: Vec2) -> Vec3 { Vec3::ZERO }
+    fn vector_potential(&self, _pos: Vec2) -> f32 { 0. }
+}
+
+// Presents the vector potential of a field as though it were an electric potential, so that
+// isopotentials traced through it are magnetic field lines.
+pub struct VectorPotential<'a, F: Field + ?Sized>(pub &'a F);
+
+impl<'a, F: Field + ?Sized> Field for VectorPotential<'a, F> {
+    // -grad(A_z), which is B turned a quarter turn clockwise
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        let b = self.0.b_field(pos);
+
+        Vec2::new(b.y, -b.x)
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        self.0.vector_potential(pos)
+    }
+}
+
+// The state of a body as seen by the integrators. Bodies which cannot rotate keep their
+// angle and angular velocity at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Kinematics {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub angle: f32,
+    pub ang_vel: f32
 }
 
+pub trait Body: Field {
+    fn pos(&self) -> Vec2;
+    fn kinematics(&self) -> Kinematics;
+    fn set_kinematics(&mut self, k: Kinematics);
+
+    // Infinite for bodies which are fixed in place or cannot rotate.
+    fn mass(&self) -> f32;
+    fn inertia(&self) -> f32;
+
+    // The body's charge distribution as a set of point charges, exact for bodies made of
+    // point charges and a quadrature rule for continuous ones.
+    fn charge_samples(&self) -> Vec<(Vec2, f32)>;
+
+    // Likewise for current through the plane, for bodies which carry any.
+    fn current_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+
+    // Net force and torque about pos() exerted on the body by an external field, which
+    // excludes the body's own. The field is integrated over the whole charge distribution,
+    // so extended bodies feel the difference in field across them. Moving charges feel
+    // q v x B as well, and currents feel I z x B.
+    fn force_torque(&self, field: &dyn Field) -> (Vec2, f32) {
+        let k = self.kinematics();
+        let center = self.pos();
+        let electric = self.charge_samples().into_iter().map(|(p, q)| {
+            let v = k.vel+k.ang_vel*(p-center).perp();
+            let lorentz = v.extend(0.).cross(field.b_field(p)).truncate();
+
+            (p, q*(field.e_field(p)+lorentz))
+        });
+        let magnetic = self.current_samples()
+                           .into_iter()
+                           .map(|(p, i)| (p, i*field.b_field(p).truncate().perp()));
+
+        electric.chain(magnetic).fold((Vec2::ZERO, 0.), |(force, torque), (p, f)| {
+            (force+f, torque+(p-center).perp_dot(f))
+        })
+    }
+
+    fn charge(&self) -> f32;
+
+    // Solid extent used for collisions, or None for bodies which cannot be touched.
+    fn shape(&self) -> Option<Shape>;
+
+    // Whether pos is in or on the body's charge, where field lines end. Tracing stops at the
+    // surface of this region rather than carrying on into the singularity inside it.
+    fn is_sink(&self, _pos: Vec2) -> bool { false }
+
+    // Whether the body's field reaches pos. A body whose field covers only part of the plane,
+    // such as a grid solution, gives NaN beyond it so that lines traced there stop, but
+    // exerts no force there on other bodies.
+    fn covers(&self, _pos: Vec2) -> bool { true }
+
+    // Points just outside the body's charge from which to trace field lines straight from it,
+    // one for every flux_step of flux leaving or reaching it, each with the sign of the
+    // charge it is next to.
+    fn field_line_seeds(&self, _flux_step: f32) -> Vec<(Vec2, f32)> { Vec::new() }
+
+    // Absorb another body which has come into contact, conserving charge, mass and momentum.
+    // Returns false, leaving both untouched, if the two cannot be merged into this one.
+    fn merge(&mut self, other: &dyn Body) -> bool;
+
+    // Radius of the disc the body's charge covers, for bodies which others can absorb when
+    // they merge, or None for those which can't be absorbed. Point charges cover none.
+    fn merge_radius(&self) -> Option<f32> { None }
+
+    // Rearrange any charge which the body lets move freely, such as that on a conductor, to
+    // suit the external field. Returns the largest change in any part of the induced charge,
+    // relative to the largest part, so that callers can repeat until everything settles.
+    fn induce(&mut self, _external: &dyn Field) -> f32 { 0. }
+
+    // The body's charge as an image boundary sees it, or None for bodies which can't be
+    // imaged, because their charge moves about under the field or they carry current.
+    fn image_sources(&self) -> Option<Vec<ImageSource>> { None }
+
+    fn draw(&self, canvas: &mut dyn Canvas);
+    fn to_scene(&self) -> SceneBody;
+}
+
+
+
+// Radius at which point-like bodies are drawn, growing with charge up to a limit. They also
+// collide at this size.
+fn glyph_radius(charge: f32) -> f32 {
+    (1.-(-charge.abs()).exp())*5.
+}
+
+// Radius of the small disc around a point charge on which field lines end. It is a fixed
+// size rather than the glyph's, and small enough that the two charges of a dipole, a unit
+// apart, are well clear of each other.
+const SINK_RADIUS: f32 = 0.2;
+
+// Combine the motion of two bodies into that of their centre of mass.
+fn combined_motion(m1: f32, k1: Kinematics, m2: f32, k2: Kinematics) -> (Vec2, Vec2) {
+    let m = m1+m2;
+
+    ((m1*k1.pos+m2*k2.pos)/m, (m1*k1.vel+m2*k2.vel)/m)
+}
+
+// Distance from the surface of a charge to the seeds of field lines around it.
+const SEED_GAP: f32 = 0.01;
+
+// Seeds evenly spaced in angle around a circle just outside a charge, one for every
+// flux_step of the flux 2πq leaving it, starting from the given angle.
+fn circle_seeds(
+    center: Vec2,
+    radius: f32,
+    charge: f32,
+    angle: f32,
+    flux_step: f32
+) -> Vec<(Vec2, f32)> {
+    if flux_step <= 0. { return Vec::new(); }
+
+    let n = (std::f32::consts::TAU*charge.abs()/flux_step).round() as usize;
+
+    (0..n).map(|i| {
+              let theta = angle+std::f32::consts::TAU*i as f32/n as f32;
+
+              (center+(radius+SEED_GAP)*Vec2::new(theta.cos(), theta.sin()), charge.signum())
+          })
+          .collect()
+}
 
+// Three point Gauss-Legendre nodes and weights on [-1, 1].
+const GAUSS_3: [(f32, f32); 3] = [(-0.774_596_7, 5./9.), (0., 8./9.), (0.774_596_7, 5./9.)];
 
 #[derive(Clone)]
 pub struct PointCharge {
@@ -22,11 +181,7 @@ pub struct PointCharge {
     pub vel: Vec2
 }
 
-impl Body for PointCharge {
-    fn pos(&self) -> Vec2 {
-        self.pos
-    }
-
+impl Field for PointCharge {
     fn e_field(&self, pos: Vec2) -> Vec2 {
         let r = pos-self.pos;
         
@@ -36,73 +191,151 @@ impl Body for PointCharge {
     fn potential(&self, pos: Vec2) -> f32 {
         -self.charge*(pos-self.pos).length().ln()
     }
+}
 
-    fn update(&mut self, e_field: Vec2, dt: f32) {
-        let dv = dt*e_field*self.charge/self.mass;
-        
-        self.pos += (self.vel+0.5*dv)*dt;
-        self.vel += dv;
+impl Body for PointCharge {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos, vel: self.vel, ..Kinematics::default() }
     }
 
-    fn draw(&self, draw: &Draw) {
-        let r = (1.-(-self.charge.abs()).exp())*5.;
+    fn set_kinematics(&mut self, k: Kinematics) {
+        self.pos = k.pos;
+        self.vel = k.vel;
+    }
 
-        draw.ellipse()
-            .color(if self.charge < 0. { BLUE } else { RED})
-            .xy(self.pos)
-            .radius(r);
+    fn mass(&self) -> f32 { self.mass }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        vec![(self.pos, self.charge)]
+    }
+
+    fn charge(&self) -> f32 { self.charge }
+
+    fn shape(&self) -> Option<Shape> {
+        Some(Shape::circle(self.pos, glyph_radius(self.charge)))
+    }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        self.charge != 0. && Shape::circle(self.pos, SINK_RADIUS).contains(pos)
+    }
+
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        circle_seeds(self.pos, SINK_RADIUS, self.charge, 0., flux_step)
+    }
+
+    // Point charges only merge with each other.
+    fn merge(&mut self, other: &dyn Body) -> bool {
+        if other.merge_radius() != Some(0.) { return false; }
+
+        let (pos, vel) = combined_motion(
+            self.mass, self.kinematics(), other.mass(), other.kinematics()
+        );
+
+        self.charge += other.charge();
+        self.mass += other.mass();
+        self.pos = pos;
+        self.vel = vel;
+        true
+    }
+
+    fn merge_radius(&self) -> Option<f32> { Some(0.) }
+
+    fn image_sources(&self) -> Option<Vec<ImageSource>> {
+        Some(vec![ImageSource::Point { pos: self.pos, charge: self.charge }])
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let r = glyph_radius(self.charge);
+
+        canvas.ellipse(self.pos, r, if self.charge < 0. { BLUE } else { RED });
+        canvas.line(self.pos-Vec2::new(r, 0.), self.pos+Vec2::new(r, 0.), r/5., WHITE);
 
-        draw.line()
-            .start(self.pos-Vec2::new(r, 0.))
-            .end(self.pos+Vec2::new(r, 0.))
-            .stroke_weight(r/5.)
-            .color(WHITE);
-        
         if self.charge >= 0. {
-            draw.line()
-                .start(self.pos-Vec2::new(0., r))
-                .end(self.pos+Vec2::new(0., r))
-                .stroke_weight(r/5.)
-                .color(WHITE);
+            canvas.line(self.pos-Vec2::new(0., r), self.pos+Vec2::new(0., r), r/5., WHITE);
+        }
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::PointCharge {
+            charge: self.charge,
+            mass: self.mass,
+            pos: self.pos,
+            vel: self.vel
         }
     }
 }
 
 
 
+// A pair of opposite point charges a unit distance apart, which translates and rotates as a
+// rigid body. The charges are kept in step with the position and orientation.
 pub struct Dipole {
     q1: PointCharge,
     q2: PointCharge,
     pos: Vec2,
+    vel: Vec2,
+    angle: f32,
+    ang_vel: f32,
+    mass: f32,
+    inertia: f32
 }
 
 impl Dipole {
     pub fn new(dipole: f32, mass: f32, angle: f32, pos: Vec2, vel: Vec2) -> Dipole {
-        let direction = Vec2::new(angle.cos(), angle.sin());
-
-        Dipole {
+        let mut d = Dipole {
             q1: PointCharge {
                 charge: dipole,
                 mass: mass/2.,
-                pos: pos+0.5*direction,
+                pos: pos,
                 vel: vel
             },
             q2: PointCharge {
                 charge: -dipole,
                 mass: mass/2.,
-                pos: pos-0.5*direction,
+                pos: pos,
                 vel: vel
             },
-            pos: pos
-        }
+            pos: pos,
+            vel: vel,
+            angle: angle,
+            ang_vel: 0.,
+            mass: mass,
+            inertia: mass/4.
+        };
+
+        d.place_charges();
+        d
     }
-}
 
-impl Body for Dipole {
-    fn pos(&self) -> Vec2 {
-        self.pos
+    pub fn with_ang_vel(mut self, ang_vel: f32) -> Self {
+        self.ang_vel = ang_vel;
+        self.place_charges();
+        self
+    }
+
+    pub fn dipole_moment(&self) -> Vec2 {
+        self.q1.charge*(self.q1.pos-self.q2.pos)
     }
 
+    // Put the charges where the position and orientation say they should be, half of the
+    // separation either side of the centre, moving with the rotation.
+    fn place_charges(&mut self) {
+        let half = 0.5*Vec2::new(self.angle.cos(), self.angle.sin());
+        let spin = self.ang_vel*half.perp();
+
+        self.q1.pos = self.pos+half;
+        self.q2.pos = self.pos-half;
+        self.q1.vel = self.vel+spin;
+        self.q2.vel = self.vel-spin;
+    }
+}
+
+impl Field for Dipole {
     fn e_field(&self, pos: Vec2) -> Vec2 {
         self.q1.e_field(pos)+self.q2.e_field(pos)
     }
@@ -110,23 +343,77 @@ impl Body for Dipole {
     fn potential(&self, pos: Vec2) -> f32 {
         self.q1.potential(pos)+self.q2.potential(pos)
     }
+}
+
+impl Body for Dipole {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos, vel: self.vel, angle: self.angle, ang_vel: self.ang_vel }
+    }
+
+    fn set_kinematics(&mut self, k: Kinematics) {
+        self.pos = k.pos;
+        self.vel = k.vel;
+        self.angle = k.angle;
+        self.ang_vel = k.ang_vel;
+        self.place_charges();
+    }
+
+    fn mass(&self) -> f32 { self.mass }
+    fn inertia(&self) -> f32 { self.inertia }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        vec![(self.q1.pos, self.q1.charge), (self.q2.pos, self.q2.charge)]
+    }
+
+    fn charge(&self) -> f32 { 0. }
+
+    fn shape(&self) -> Option<Shape> {
+        Some(Shape::circle(self.pos, glyph_radius(self.q1.charge)))
+    }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        self.q1.is_sink(pos) || self.q2.is_sink(pos)
+    }
+
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        let (q1, q2) = (&self.q1, &self.q2);
+        let mut seeds = circle_seeds(q1.pos, SINK_RADIUS, q1.charge, self.angle, flux_step);
+
+        seeds.extend(circle_seeds(q2.pos, SINK_RADIUS, q2.charge, self.angle, flux_step));
+        seeds
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
 
-    fn update(&mut self, e_field: Vec2, dt: f32) {
-        ; // todo
+    fn image_sources(&self) -> Option<Vec<ImageSource>> {
+        Some(self.charge_samples()
+                 .into_iter()
+                 .map(|(pos, charge)| ImageSource::Point { pos: pos, charge: charge })
+                 .collect())
     }
 
-    fn draw(&self, draw: &Draw) {
-        let r = (1.-(-self.q1.charge).exp())*5.;
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let r = glyph_radius(self.q1.charge);
         let forward = r*(self.q1.pos-self.pos)*2.;
         let side = forward.perp()/2.;
-        
-        draw.tri()
-            .color(RED)
-            .points(self.pos+forward, self.pos+side, self.pos-side);
-        
-        draw.tri()
-            .color(BLUE)
-            .points(self.pos-forward, self.pos-side, self.pos+side);
+
+        canvas.tri([self.pos+forward, self.pos+side, self.pos-side], RED);
+        canvas.tri([self.pos-forward, self.pos-side, self.pos+side], BLUE);
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::Dipole {
+            dipole: self.q1.charge,
+            mass: self.mass,
+            angle: self.angle,
+            pos: self.pos,
+            vel: self.vel,
+            ang_vel: self.ang_vel
+        }
     }
 }
 
@@ -140,11 +427,7 @@ pub struct CircleCharge {
     pub vel: Vec2
 }
 
-impl Body for CircleCharge {
-    fn pos(&self) -> Vec2 {
-        self.pos
-    }
-
+impl Field for CircleCharge {
     fn e_field(&self, pos: Vec2) -> Vec2 {
         let r = pos-self.pos;
         let r_sq = r.length_squared();
@@ -159,22 +442,105 @@ impl Body for CircleCharge {
         let r_sq = r.length_squared();
         let r0_sq = self.radius*self.radius;
 
-        if r_sq >= r0_sq { -self.charge*r_sq/(2.*r0_sq) }
-        else { ((r0_sq/r_sq).ln()-1.)/2. }
+        // Outside it is a point charge, and inside the potential follows from the linear
+        // field, meeting the outside at the radius
+        if r_sq >= r0_sq { -self.charge*r_sq.ln()/2. }
+        else { -self.charge*(r0_sq.ln()+r_sq/r0_sq-1.)/2. }
     }
+}
 
-    fn update(&mut self, e_field: Vec2, dt: f32) {
-        let dv = dt*e_field*self.charge/self.mass;
-        
-        self.pos += (self.vel+0.5*dv)*dt;
-        self.vel += dv;
+impl Body for CircleCharge {
+    fn pos(&self) -> Vec2 {
+        self.pos
     }
 
-    fn draw(&self, draw: &Draw) {
-        let d = draw.ellipse().radius(self.radius).xy(self.pos);
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos, vel: self.vel, ..Kinematics::default() }
+    }
 
-        if self.charge < 0. { d.rgba(0., 0., 1., 0.5); }
-        else { d.rgba(1., 0., 0., 0.5); }
+    fn set_kinematics(&mut self, k: Kinematics) {
+        self.pos = k.pos;
+        self.vel = k.vel;
+    }
+
+    fn mass(&self) -> f32 { self.mass }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    // Gauss-Legendre in radius, weighted by the area of each ring, and evenly spaced in
+    // angle, which integrates the field over the disc rather than just at its centre.
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        const ANGLES: usize = 12;
+        let density = self.charge/(std::f32::consts::PI*self.radius*self.radius);
+        let d_theta = std::f32::consts::TAU/ANGLES as f32;
+        let mut samples = Vec::with_capacity(GAUSS_3.len()*ANGLES);
+
+        for (x, w) in GAUSS_3 {
+            let r = 0.5*self.radius*(1.+x);
+            let q = density*0.5*self.radius*w*r*d_theta;
+
+            for i in 0..ANGLES {
+                let theta = i as f32*d_theta;
+
+                samples.push((self.pos+r*Vec2::new(theta.cos(), theta.sin()), q));
+            }
+        }
+
+        samples
+    }
+
+    fn charge(&self) -> f32 { self.charge }
+
+    fn shape(&self) -> Option<Shape> {
+        Some(Shape::circle(self.pos, self.radius))
+    }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        Shape::circle(self.pos, self.radius).contains(pos)
+    }
+
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        circle_seeds(self.pos, self.radius, self.charge, 0., flux_step)
+    }
+
+    // Circle charges absorb point charges and other circle charges, keeping the total area.
+    fn merge(&mut self, other: &dyn Body) -> bool {
+        let other_radius = match other.merge_radius() {
+            Some(radius) => radius,
+            None => { return false; }
+        };
+        let (pos, vel) = combined_motion(
+            self.mass, self.kinematics(), other.mass(), other.kinematics()
+        );
+
+        self.charge += other.charge();
+        self.mass += other.mass();
+        self.radius = (self.radius*self.radius+other_radius*other_radius).sqrt();
+        self.pos = pos;
+        self.vel = vel;
+        true
+    }
+
+    fn merge_radius(&self) -> Option<f32> { Some(self.radius) }
+
+    fn image_sources(&self) -> Option<Vec<ImageSource>> {
+        Some(vec![ImageSource::Circle { pos: self.pos, radius: self.radius, charge: self.charge }])
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let color = if self.charge < 0. { Color::rgba(0., 0., 1., 0.5) }
+                    else { Color::rgba(1., 0., 0., 0.5) };
+
+        canvas.ellipse(self.pos, self.radius, color);
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::CircleCharge {
+            charge: self.charge,
+            mass: self.mass,
+            radius: self.radius,
+            pos: self.pos,
+            vel: self.vel
+        }
     }
 }
 
@@ -184,25 +550,88 @@ pub struct GlobalField {
     pub field: Vec2
 }
 
+impl Field for GlobalField {
+    fn e_field(&self, _pos: Vec2) -> Vec2 {
+        self.field
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        -pos.dot(self.field)
+    }
+}
+
 impl Body for GlobalField {
     fn pos(&self) -> Vec2 {
         Vec2::ZERO
     }
 
-    fn e_field(&self, _pos: Vec2) -> Vec2 {
-        self.field
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos(), ..Kinematics::default() }
     }
 
-    fn potential(&self, pos: Vec2) -> f32 {
-        -pos.dot(self.field)
+    fn set_kinematics(&mut self, _k: Kinematics) {}
+    fn mass(&self) -> f32 { f32::INFINITY }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+    fn charge(&self) -> f32 { 0. }
+    fn shape(&self) -> Option<Shape> { None }
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn draw(&self, _canvas: &mut dyn Canvas) {}
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::GlobalField { field: self.field }
+    }
+}
+
+
+
+// A uniform magnetic field pointing out of the plane, or into it if negative. Positive
+// charges moving through it circle clockwise when it is positive.
+pub struct GlobalMagneticField {
+    pub field: f32
+}
+
+// The vector potential of a uniform out-of-plane field lies in the plane, so A_z is zero and
+// there are no in-plane field lines to trace.
+impl Field for GlobalMagneticField {
+    fn e_field(&self, _pos: Vec2) -> Vec2 { Vec2::ZERO }
+    fn potential(&self, _pos: Vec2) -> f32 { 0. }
+
+    fn b_field(&self, _pos: Vec2) -> Vec3 {
+        Vec3::new(0., 0., self.field)
+    }
+}
+
+impl Body for GlobalMagneticField {
+    fn pos(&self) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos(), ..Kinematics::default() }
     }
 
-    fn update(&mut self, _e_field: Vec2, _dt: f32) {}
-    fn draw(&self, _draw: &Draw) {}
+    fn set_kinematics(&mut self, _k: Kinematics) {}
+    fn mass(&self) -> f32 { f32::INFINITY }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+    fn charge(&self) -> f32 { 0. }
+    fn shape(&self) -> Option<Shape> { None }
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn draw(&self, _canvas: &mut dyn Canvas) {}
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::GlobalMagneticField { field: self.field }
+    }
 }
 
 
 
+// A uniformly charged straight segment, which translates and rotates about its centre as a
+// rigid rod. It is fixed in place unless given a finite mass.
 pub struct LineCharge {
     start: Vec2,
     end: Vec2,
@@ -210,31 +639,54 @@ pub struct LineCharge {
     length: f32,
     charge_density: f32,
     rot: Mat2,
-    rot_inv: Mat2
+    rot_inv: Mat2,
+    angle: f32,
+    vel: Vec2,
+    ang_vel: f32,
+    mass: f32
 }
 
 impl LineCharge {
     pub fn new(start: Vec2, end: Vec2, charge: f32) -> Self {
-        let theta = (end-start).angle();
+        let dir = end-start;
         let length = start.distance(end);
-
-        Self {
+        let mut line = Self {
             start: start,
             end: end,
             center: (start+end)/2.,
             length: length,
             charge_density: charge/length,
-            rot: Mat2::from_angle(theta),
-            rot_inv: Mat2::from_angle(-theta)
-        }
+            rot: Mat2::IDENTITY,
+            rot_inv: Mat2::IDENTITY,
+            angle: dir.y.atan2(dir.x),
+            vel: Vec2::ZERO,
+            ang_vel: 0.,
+            mass: f32::INFINITY
+        };
+
+        line.place_ends();
+        line
     }
-}
 
-impl Body for LineCharge {
-    fn pos(&self) -> Vec2 {
-        (self.start+self.end)/2.
+    pub fn with_motion(mut self, mass: f32, vel: Vec2, ang_vel: f32) -> Self {
+        self.mass = mass;
+        self.vel = vel;
+        self.ang_vel = ang_vel;
+        self
+    }
+
+    // Put the ends where the centre and orientation say they should be.
+    fn place_ends(&mut self) {
+        let half = 0.5*self.length*Vec2::new(self.angle.cos(), self.angle.sin());
+
+        self.start = self.center-half;
+        self.end = self.center+half;
+        self.rot = Mat2::from_angle(self.angle);
+        self.rot_inv = Mat2::from_angle(-self.angle);
     }
+}
 
+impl Field for LineCharge {
     fn e_field(&self, pos: Vec2) -> Vec2 {
         let p = self.rot_inv*(pos-self.center);
         let (x, y) = (p[0], p[1]);
@@ -257,25 +709,296 @@ impl Body for LineCharge {
 
         0.5*self.charge_density*(db_sq.ln()*(x-d)-da_sq.ln()*(x+d)-2.*y*theta+4.*d)
     }
+}
+
+impl Body for LineCharge {
+    fn pos(&self) -> Vec2 {
+        self.center
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.center, vel: self.vel, angle: self.angle, ang_vel: self.ang_vel }
+    }
+
+    fn set_kinematics(&mut self, k: Kinematics) {
+        self.center = k.pos;
+        self.vel = k.vel;
+        self.angle = k.angle;
+        self.ang_vel = k.ang_vel;
+        self.place_ends();
+    }
+
+    fn mass(&self) -> f32 { self.mass }
+
+    // A thin rod about its centre.
+    fn inertia(&self) -> f32 {
+        self.mass*self.length*self.length/12.
+    }
+
+    // Composite Gauss-Legendre along the segment. The field from nearby bodies can vary
+    // sharply along a long line, so it is split into several panels.
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        const PANELS: usize = 8;
+        let panel = (self.end-self.start)/PANELS as f32;
+        let q = self.charge_density*self.length/PANELS as f32;
+        let mut samples = Vec::with_capacity(PANELS*GAUSS_3.len());
+
+        for i in 0..PANELS {
+            let mid = self.start+(i as f32+0.5)*panel;
+
+            samples.extend(GAUSS_3.iter().map(|(x, w)| (mid+0.5*x*panel, 0.5*w*q)));
+        }
+
+        samples
+    }
+
+    fn charge(&self) -> f32 { self.charge_density*self.length }
+
+    // Line charges are solid walls, as thick as they are drawn.
+    fn shape(&self) -> Option<Shape> {
+        Some(Shape { start: self.start, end: self.end, radius: 1. })
+    }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        Shape { start: self.start, end: self.end, radius: 1. }.contains(pos)
+    }
+
+    // Evenly spaced along both sides, as the charge density is even. Close to the line each
+    // side carries half of the flux.
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        if flux_step <= 0. { return Vec::new(); }
+
+        let charge = self.charge();
+        let n = (std::f32::consts::PI*charge.abs()/flux_step).round() as usize;
+        let side = (1.+SEED_GAP)*(self.rot*Vec2::Y);
+        let mut seeds = Vec::with_capacity(2*n);
+
+        for i in 0..n {
+            let p = self.start.lerp(self.end, (i as f32+0.5)/n as f32);
+
+            seeds.push((p+side, charge.signum()));
+            seeds.push((p-side, charge.signum()));
+        }
+
+        seeds
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn image_sources(&self) -> Option<Vec<ImageSource>> {
+        Some(vec![ImageSource::Segment { start: self.start, end: self.end, charge: self.charge() }])
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let color = if self.charge_density < 0. { BLUE } else { RED };
+
+        canvas.line(self.start, self.end, 2., color);
+    }
 
-    fn update(&mut self, _e_field: Vec2, _dt: f32) {}
-    
-    fn draw(&self, draw: &Draw) {
-        draw.line()
-            .start(self.start)
-            .end(self.end)
-            .color(if self.charge_density < 0. { BLUE } else { RED })
-            .stroke_weight(2.);
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::LineCharge {
+            start: self.start,
+            end: self.end,
+            charge: self.charge_density*self.length,
+            mass: if self.mass.is_finite() { Some(self.mass) } else { None },
+            vel: self.vel,
+            ang_vel: self.ang_vel
+        }
     }
 }
 
 
 
-impl<C: DerefMut<Target=[Box<dyn Body>]>> Body for C {
+// An infinite straight wire carrying current out of the plane, or into it if the current is
+// negative. Its magnetic field circles it with the same shape as a point charge's electric
+// field, turned a quarter turn anticlockwise.
+#[derive(Clone)]
+pub struct CurrentWire {
+    pub current: f32,
+    pub mass: f32,
+    pub pos: Vec2,
+    pub vel: Vec2
+}
+
+impl Field for CurrentWire {
+    fn e_field(&self, _pos: Vec2) -> Vec2 { Vec2::ZERO }
+    fn potential(&self, _pos: Vec2) -> f32 { 0. }
+
+    fn b_field(&self, pos: Vec2) -> Vec3 {
+        let r = pos-self.pos;
+
+        (r.perp()*self.current/r.length_squared()).extend(0.)
+    }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        -self.current*(pos-self.pos).length().ln()
+    }
+}
+
+impl Body for CurrentWire {
     fn pos(&self) -> Vec2 {
-        self.iter().fold(Vec2::ZERO, |acc, b| acc+b.pos())/self.len() as f32
+        self.pos
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos, vel: self.vel, ..Kinematics::default() }
+    }
+
+    fn set_kinematics(&mut self, k: Kinematics) {
+        self.pos = k.pos;
+        self.vel = k.vel;
+    }
+
+    fn mass(&self) -> f32 { self.mass }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+
+    fn current_samples(&self) -> Vec<(Vec2, f32)> {
+        vec![(self.pos, self.current)]
+    }
+
+    fn charge(&self) -> f32 { 0. }
+
+    fn shape(&self) -> Option<Shape> {
+        Some(Shape::circle(self.pos, glyph_radius(self.current)))
     }
 
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    // A dot for current out of the plane and a cross for current into it.
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let r = glyph_radius(self.current);
+
+        canvas.ellipse(self.pos, r, GREY);
+
+        if self.current >= 0. { canvas.ellipse(self.pos, r/4., BLACK); }
+        else {
+            let d = r*0.5;
+
+            canvas.line(self.pos-Vec2::new(d, d), self.pos+Vec2::new(d, d), r/5., BLACK);
+            canvas.line(self.pos-Vec2::new(d, -d), self.pos+Vec2::new(d, -d), r/5., BLACK);
+        }
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::CurrentWire {
+            current: self.current,
+            mass: self.mass,
+            pos: self.pos,
+            vel: self.vel
+        }
+    }
+}
+
+
+
+// A flat strip of current flowing out of the plane, the magnetic counterpart of a line
+// charge. Its fields are those of a line charge carrying the same total, turned a quarter
+// turn, so it moves as the same rigid rod.
+pub struct CurrentSheet {
+    line: LineCharge
+}
+
+impl CurrentSheet {
+    pub fn new(start: Vec2, end: Vec2, current: f32) -> Self {
+        Self { line: LineCharge::new(start, end, current) }
+    }
+
+    pub fn with_motion(self, mass: f32, vel: Vec2, ang_vel: f32) -> Self {
+        Self { line: self.line.with_motion(mass, vel, ang_vel) }
+    }
+}
+
+impl Field for CurrentSheet {
+    fn e_field(&self, _pos: Vec2) -> Vec2 { Vec2::ZERO }
+    fn potential(&self, _pos: Vec2) -> f32 { 0. }
+
+    fn b_field(&self, pos: Vec2) -> Vec3 {
+        self.line.e_field(pos).perp().extend(0.)
+    }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        self.line.potential(pos)
+    }
+}
+
+impl Body for CurrentSheet {
+    fn pos(&self) -> Vec2 { self.line.pos() }
+    fn kinematics(&self) -> Kinematics { self.line.kinematics() }
+    fn set_kinematics(&mut self, k: Kinematics) { self.line.set_kinematics(k) }
+    fn mass(&self) -> f32 { self.line.mass() }
+    fn inertia(&self) -> f32 { self.line.inertia() }
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+    fn current_samples(&self) -> Vec<(Vec2, f32)> { self.line.charge_samples() }
+    fn charge(&self) -> f32 { 0. }
+    fn shape(&self) -> Option<Shape> { self.line.shape() }
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        canvas.line(self.line.start, self.line.end, 2., GREY);
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        let mass = self.line.mass;
+
+        SceneBody::CurrentSheet {
+            start: self.line.start,
+            end: self.line.end,
+            current: self.line.charge(),
+            mass: if mass.is_finite() { Some(mass) } else { None },
+            vel: self.line.vel,
+            ang_vel: self.line.ang_vel
+        }
+    }
+}
+
+
+
+// The field of every body in a list except one, split around it.
+pub(crate) struct Others<'a> {
+    pub before: &'a [Box<dyn Body>],
+    pub after: &'a [Box<dyn Body>]
+}
+
+impl<'a> Others<'a> {
+    // The other bodies whose fields reach pos.
+    fn covering(&self, pos: Vec2) -> impl Iterator<Item = &'a Box<dyn Body>> {
+        self.before.iter().chain(self.after).filter(move |b| b.covers(pos))
+    }
+}
+
+impl<'a> Field for Others<'a> {
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        self.covering(pos).fold(Vec2::ZERO, |acc, b| acc+b.e_field(pos))
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        self.covering(pos).map(|b| b.potential(pos)).sum()
+    }
+
+    fn b_field(&self, pos: Vec2) -> Vec3 {
+        self.covering(pos).fold(Vec3::ZERO, |acc, b| acc+b.b_field(pos))
+    }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        self.covering(pos).map(|b| b.vector_potential(pos)).sum()
+    }
+}
+
+// The combined field of two others.
+pub(crate) struct Sum<'a>(pub &'a dyn Field, pub &'a dyn Field);
+
+impl<'a> Field for Sum<'a> {
+    fn e_field(&self, pos: Vec2) -> Vec2 { self.0.e_field(pos)+self.1.e_field(pos) }
+    fn potential(&self, pos: Vec2) -> f32 { self.0.potential(pos)+self.1.potential(pos) }
+    fn b_field(&self, pos: Vec2) -> Vec3 { self.0.b_field(pos)+self.1.b_field(pos) }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        self.0.vector_potential(pos)+self.1.vector_potential(pos)
+    }
+}
+
+impl Field for [Box<dyn Body>] {
     fn e_field(&self, pos: Vec2) -> Vec2 {
         self.iter().fold(Vec2::ZERO, |acc, b| acc+b.e_field(pos))
     }
@@ -284,11 +1007,132 @@ impl<C: DerefMut<Target=[Box<dyn Body>]>> Body for C {
         self.iter().map(|b| b.potential(pos)).sum()
     }
 
-    fn update(&mut self, e_field: Vec2, dt: f32) {
-        for b in self.iter_mut() { b.update(e_field, dt); }
+    fn b_field(&self, pos: Vec2) -> Vec3 {
+        self.iter().fold(Vec3::ZERO, |acc, b| acc+b.b_field(pos))
+    }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        self.iter().map(|b| b.vector_potential(pos)).sum()
     }
+}
+
+impl Body for [Box<dyn Body>] {
+    fn pos(&self) -> Vec2 {
+        self.iter().fold(Vec2::ZERO, |acc, b| acc+b.pos())/self.len() as f32
+    }
+
+    // A group moves as one rigid body without rotating, and is fixed if any member is.
+    fn kinematics(&self) -> Kinematics {
+        let momentum = self.iter().fold(Vec2::ZERO, |acc, b| acc+b.mass()*b.kinematics().vel);
+        let mass = self.mass();
+        let vel = if mass.is_finite() && mass > 0. { momentum/mass } else { Vec2::ZERO };
+
+        Kinematics { pos: self.pos(), vel: vel, ..Kinematics::default() }
+    }
+
+    fn set_kinematics(&mut self, k: Kinematics) {
+        let shift = k.pos-self.pos();
+
+        for b in self.iter_mut() {
+            let mut bk = b.kinematics();
+
+            bk.pos += shift;
+            bk.vel = k.vel;
+            b.set_kinematics(bk);
+        }
+    }
+
+    fn mass(&self) -> f32 {
+        self.iter().map(|b| b.mass()).sum()
+    }
+
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        self.iter().flat_map(|b| b.charge_samples()).collect()
+    }
+
+    fn current_samples(&self) -> Vec<(Vec2, f32)> {
+        self.iter().flat_map(|b| b.current_samples()).collect()
+    }
+
+    fn charge(&self) -> f32 {
+        self.iter().map(|b| b.charge()).sum()
+    }
+
+    fn shape(&self) -> Option<Shape> { None }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        self.iter().any(|b| b.is_sink(pos))
+    }
+
+    // The group's field is NaN wherever any member's is.
+    fn covers(&self, pos: Vec2) -> bool {
+        self.iter().all(|b| b.covers(pos))
+    }
+
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        self.iter().flat_map(|b| b.field_line_seeds(flux_step)).collect()
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    // Each member sees the external field along with the field of the rest of the group.
+    fn induce(&mut self, external: &dyn Field) -> f32 {
+        let mut change: f32 = 0.;
+
+        for i in 0..self.len() {
+            let (before, rest) = self.split_at_mut(i);
+            let (body, after) = rest.split_first_mut().unwrap();
+            let others = Sum(external, &Others { before: before, after: after });
+
+            change = change.max(body.induce(&others));
+        }
+
+        change
+    }
+
+    // A group can only be imaged if every member can.
+    fn image_sources(&self) -> Option<Vec<ImageSource>> {
+        self.iter().map(|b| b.image_sources()).collect::<Option<Vec<_>>>().map(|v| v.concat())
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        for b in self.iter() { b.draw(canvas); }
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::Group(self.iter().map(|b| b.to_scene()).collect())
+    }
+}
+
+impl<C: DerefMut<Target=[Box<dyn Body>]> + Send + Sync> Field for C {
+    fn e_field(&self, pos: Vec2) -> Vec2 { (**self).e_field(pos) }
+    fn potential(&self, pos: Vec2) -> f32 { (**self).potential(pos) }
+    fn b_field(&self, pos: Vec2) -> Vec3 { (**self).b_field(pos) }
+    fn vector_potential(&self, pos: Vec2) -> f32 { (**self).vector_potential(pos) }
+}
+
+impl<C: DerefMut<Target=[Box<dyn Body>]> + Send + Sync> Body for C {
+    fn pos(&self) -> Vec2 { (**self).pos() }
+    fn kinematics(&self) -> Kinematics { (**self).kinematics() }
+    fn set_kinematics(&mut self, k: Kinematics) { (**self).set_kinematics(k) }
+    fn mass(&self) -> f32 { (**self).mass() }
+    fn inertia(&self) -> f32 { (**self).inertia() }
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { (**self).charge_samples() }
+    fn current_samples(&self) -> Vec<(Vec2, f32)> { (**self).current_samples() }
+    fn charge(&self) -> f32 { (**self).charge() }
+    fn shape(&self) -> Option<Shape> { (**self).shape() }
+    fn is_sink(&self, pos: Vec2) -> bool { (**self).is_sink(pos) }
+    fn covers(&self, pos: Vec2) -> bool { (**self).covers(pos) }
 
-    fn draw(&self, draw: &Draw) {
-        for b in self.iter() { b.draw(draw); }
+    fn field_line_seeds(&self, flux_step: f32) -> Vec<(Vec2, f32)> {
+        (**self).field_line_seeds(flux_step)
     }
+    fn merge(&mut self, other: &dyn Body) -> bool { (**self).merge(other) }
+    fn merge_radius(&self) -> Option<f32> { (**self).merge_radius() }
+    fn induce(&mut self, external: &dyn Field) -> f32 { (**self).induce(external) }
+    fn image_sources(&self) -> Option<Vec<ImageSource>> { (**self).image_sources() }
+    fn draw(&self, canvas: &mut dyn Canvas) { (**self).draw(canvas) }
+    fn to_scene(&self) -> SceneBody { (**self).to_scene() }
 }
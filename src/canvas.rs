@@ -0,0 +1,112 @@
+use glam::Vec2;
+use crate::bodies::Body;
+use crate::images::{ImageBoundary, DRAWN_SAMPLES};
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+impl Color {
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r: r, g: g, b: b, a: a }
+    }
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.)
+    }
+}
+
+pub const BLACK: Color = Color::rgb(0., 0., 0.);
+pub const WHITE: Color = Color::rgb(1., 1., 1.);
+pub const RED: Color = Color::rgb(1., 0., 0.);
+pub const BLUE: Color = Color::rgb(0., 0., 1.);
+pub const YELLOW: Color = Color::rgb(1., 1., 0.);
+pub const CYAN: Color = Color::rgb(0., 1., 1.);
+pub const GREY: Color = Color::rgb(0.5, 0.5, 0.5);
+
+
+
+// Something bodies can draw themselves onto, in world coordinates with y pointing up. This
+// keeps the library free of any particular graphics backend.
+pub trait Canvas {
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: Color);
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: Color);
+    fn tri(&mut self, points: [Vec2; 3], color: Color);
+    fn polyline(&mut self, points: &[Vec2], weight: f32, color: Color);
+}
+
+
+
+// A rectangular window onto the world, rendered at a given pixel size. World coordinates
+// have y pointing up and pixel coordinates have y pointing down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub center: Vec2,
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Viewport {
+    // A viewport matching the GUI window, where one world unit is one pixel.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { center: Vec2::ZERO, scale: 1., width: width, height: height }
+    }
+
+    pub fn to_pixel(&self, pos: Vec2) -> Vec2 {
+        let r = (pos-self.center)*self.scale;
+
+        Vec2::new(self.width as f32/2.+r.x, self.height as f32/2.-r.y)
+    }
+
+    pub fn to_world(&self, pixel: Vec2) -> Vec2 {
+        let r = Vec2::new(pixel.x-self.width as f32/2., self.height as f32/2.-pixel.y);
+
+        self.center+r/self.scale
+    }
+
+    // A viewport over the same part of the world with pixels cell times the size, rounding
+    // up so that the whole of this one is covered.
+    pub fn coarsened(&self, cell: u32) -> Self {
+        let cell = cell.max(1);
+
+        Self {
+            center: self.center,
+            scale: self.scale/cell as f32,
+            width: self.width.div_ceil(cell),
+            height: self.height.div_ceil(cell)
+        }
+    }
+
+    // World space corners, bottom left then top right.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let half = Vec2::new(self.width as f32, self.height as f32)/(2.*self.scale);
+
+        (self.center-half, self.center+half)
+    }
+}
+
+
+
+// Draw everything the GUI shows: any image boundary, isopotentials in white, field lines
+// in yellow, magnetic field lines in cyan and then the bodies on top.
+pub fn draw_scene(
+    canvas: &mut dyn Canvas,
+    bodies: &[Box<dyn Body>],
+    boundary: Option<&ImageBoundary>,
+    isopotentials: &[Vec<Vec2>],
+    field_lines: &[Vec<Vec2>],
+    magnetic_lines: &[Vec<Vec2>]
+) {
+    if let Some(b) = boundary { b.draw(canvas, &b.surface_charge(bodies, DRAWN_SAMPLES)); }
+    for points in isopotentials { canvas.polyline(points, 1., WHITE); }
+    for points in field_lines { canvas.polyline(points, 1., YELLOW); }
+    for points in magnetic_lines { canvas.polyline(points, 1., CYAN); }
+    for b in bodies { b.draw(canvas); }
+}
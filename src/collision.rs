@@ -0,0 +1,193 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use crate::bodies::Body;
+
+
+
+// The solid extent of a body: every point within radius of the segment from start to end.
+// A circle is a capsule whose ends coincide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shape {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub radius: f32
+}
+
+impl Shape {
+    pub fn circle(center: Vec2, radius: f32) -> Self {
+        Self { start: center, end: center, radius: radius }
+    }
+
+    pub fn closest_point(&self, p: Vec2) -> Vec2 {
+        let d = self.end-self.start;
+        let len_sq = d.length_squared();
+
+        if len_sq == 0. { return self.start; }
+
+        self.start+d*((p-self.start).dot(d)/len_sq).clamp(0., 1.)
+    }
+
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.closest_point(p).distance_squared(p) <= self.radius*self.radius
+    }
+}
+
+struct Contact {
+    // Unit normal pointing from the first body to the second
+    normal: Vec2,
+    point: Vec2,
+    depth: f32
+}
+
+// Closest pair of points between the segments of two capsules, one on each.
+fn closest_points(a: &Shape, b: &Shape) -> (Vec2, Vec2) {
+    let (da, db, r) = (a.end-a.start, b.end-b.start, a.start-b.start);
+    let (len_a, len_b) = (da.length_squared(), db.length_squared());
+
+    if len_a == 0. { return (a.start, b.closest_point(a.start)); }
+    if len_b == 0. { return (a.closest_point(b.start), b.start); }
+
+    let (c, f, cross) = (da.dot(r), db.dot(r), da.dot(db));
+    let denom = len_a*len_b-cross*cross;
+    // Parallel segments have a whole range of closest pairs, of which any will do
+    let s = if denom > 0. { ((cross*f-c*len_b)/denom).clamp(0., 1.) } else { 0. };
+    let t = (cross*s+f)/len_b;
+    let (s, t) = if t < 0. { ((-c/len_a).clamp(0., 1.), 0.) }
+                 else if t > 1. { (((cross-c)/len_a).clamp(0., 1.), 1.) }
+                 else { (s, t) };
+
+    (a.start+da*s, b.start+db*t)
+}
+
+// Contact between two capsules, found from the closest pair of points between their
+// segments.
+fn contact(a: &Shape, b: &Shape) -> Option<Contact> {
+    let (pa, pb) = closest_points(a, b);
+    let dist = pa.distance(pb);
+    let depth = a.radius+b.radius-dist;
+
+    if depth <= 0. { return None; }
+    // Rounding keeps the closest points of crossing segments from quite meeting, and leaves
+    // the direction between them meaningless
+    if dist <= 1e-4*(a.radius+b.radius) { return Some(crossing(a, b, pa)); }
+
+    let normal = (pb-pa)/dist;
+
+    Some(Contact { normal: normal, point: pa+normal*a.radius, depth: depth })
+}
+
+// Segments which touch or cross have no direction between their closest points, so they
+// are separated across whichever of the two segments needs the shallowest push, moving the
+// other segment's ends all to one side of it.
+fn crossing(a: &Shape, b: &Shape, point: Vec2) -> Contact {
+    let reach = a.radius+b.radius;
+    let mut pushes = Vec::with_capacity(4);
+
+    // The sign turns a push on a into the normal from a to b
+    for (line, other, sign) in [(a, b, 1.), (b, a, -1.)] {
+        let axis = (line.end-line.start).perp().normalize_or_zero();
+
+        if axis == Vec2::ZERO { continue; }
+
+        let ends = [(other.start-line.start).dot(axis), (other.end-line.start).dot(axis)];
+        let (low, high) = (ends[0].min(ends[1]), ends[0].max(ends[1]));
+
+        pushes.extend([(axis*sign, reach-low), (-axis*sign, reach+high)]);
+    }
+
+    // Two coincident circles can go either way
+    let (normal, depth) = pushes.into_iter()
+                                .min_by(|x, y| x.1.total_cmp(&y.1))
+                                .unwrap_or((Vec2::X, reach));
+
+    Contact { normal: normal, point: point, depth: depth }
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Collisions {
+    // 1 for perfectly elastic collisions, 0 for bodies which stop dead
+    pub restitution: f32,
+    // Merge bodies on contact where they can be merged, instead of bouncing
+    pub merge: bool
+}
+
+impl Default for Collisions {
+    fn default() -> Self {
+        Self { restitution: 1., merge: false }
+    }
+}
+
+fn inverse(x: f32) -> f32 {
+    if x.is_finite() && x > 0. { 1./x } else { 0. }
+}
+
+// Exchange an impulse between two touching bodies and push them apart.
+fn bounce(a: &mut dyn Body, b: &mut dyn Body, c: &Contact, restitution: f32) {
+    let (mut ka, mut kb) = (a.kinematics(), b.kinematics());
+    let (inv_ma, inv_mb) = (inverse(a.mass()), inverse(b.mass()));
+    let (inv_ia, inv_ib) = (inverse(a.inertia()), inverse(b.inertia()));
+
+    if inv_ma+inv_mb == 0. { return; }
+
+    let (ra, rb) = (c.point-ka.pos, c.point-kb.pos);
+    let va = ka.vel+ka.ang_vel*ra.perp();
+    let vb = kb.vel+kb.ang_vel*rb.perp();
+    let approach = (vb-va).dot(c.normal);
+
+    if approach < 0. {
+        let (ra_n, rb_n) = (ra.perp_dot(c.normal), rb.perp_dot(c.normal));
+        let effective = inv_ma+inv_mb+ra_n*ra_n*inv_ia+rb_n*rb_n*inv_ib;
+        let j = -(1.+restitution)*approach/effective;
+
+        ka.vel -= j*inv_ma*c.normal;
+        kb.vel += j*inv_mb*c.normal;
+        ka.ang_vel -= j*ra_n*inv_ia;
+        kb.ang_vel += j*rb_n*inv_ib;
+    }
+
+    let push = c.normal*c.depth/(inv_ma+inv_mb);
+
+    ka.pos -= push*inv_ma;
+    kb.pos += push*inv_mb;
+    a.set_kinematics(ka);
+    b.set_kinematics(kb);
+}
+
+// Find every pair of touching bodies and either merge or bounce them.
+pub fn resolve(bodies: &mut Vec<Box<dyn Body>>, settings: &Collisions) {
+    let mut i = 0;
+
+    while i < bodies.len() {
+        let mut j = i+1;
+
+        while j < bodies.len() {
+            let (head, tail) = bodies.split_at_mut(j);
+            let (a, b) = (&mut head[i], &mut tail[0]);
+            let c = match (a.shape(), b.shape()) {
+                (Some(sa), Some(sb)) => contact(&sa, &sb),
+                _ => None
+            };
+
+            if let Some(c) = c {
+                if settings.merge && a.merge(&**b) {
+                    bodies.remove(j);
+                    continue;
+                }
+
+                if settings.merge && b.merge(&**a) {
+                    bodies.swap(i, j);
+                    bodies.remove(j);
+                    continue;
+                }
+
+                bounce(&mut **a, &mut **b, &c, settings.restitution);
+            }
+
+            j += 1;
+        }
+
+        i += 1;
+    }
+}
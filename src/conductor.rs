@@ -0,0 +1,363 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use crate::bodies::{Body, Field, Kinematics, LineCharge};
+use crate::canvas::*;
+use crate::collision::Shape;
+use crate::scene::SceneBody;
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Outline {
+    // Corners in order around the edge, which closes back to the first
+    Polygon(Vec<Vec2>),
+    Circle { center: Vec2, radius: f32 }
+}
+
+impl Outline {
+    // The closed loop of points around the outline.
+    fn points(&self, panels: usize) -> Vec<Vec2> {
+        match self {
+            Self::Polygon(corners) => {
+                let mut points = corners.clone();
+
+                points.push(corners[0]);
+                points
+            }
+            Self::Circle { center, radius } => {
+                (0..=panels).map(|i| {
+                                let theta = TAU*i as f32/panels as f32;
+
+                                *center+*radius*Vec2::new(theta.cos(), theta.sin())
+                            })
+                            .collect()
+            }
+        }
+    }
+
+    fn perimeter(&self) -> f32 {
+        self.points(64).windows(2).map(|w| w[0].distance(w[1])).sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    // Held at a potential, taking whatever charge that needs
+    Potential(f32),
+    // Isolated with a total charge, taking whatever potential that gives
+    Floating(f32)
+}
+
+// LU decomposition with partial pivoting of a dense square matrix, stored row by row.
+pub(crate) struct Lu {
+    n: usize,
+    a: Vec<f64>,
+    pivots: Vec<usize>
+}
+
+impl Lu {
+    pub(crate) fn new(mut a: Vec<f64>, n: usize) -> Self {
+        let mut pivots = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let p = (k..n).max_by(|&i, &j| a[i*n+k].abs().total_cmp(&a[j*n+k].abs())).unwrap();
+
+            pivots.push(p);
+
+            if p != k {
+                for c in 0..n { a.swap(k*n+c, p*n+c); }
+            }
+
+            for i in k+1..n {
+                let f = a[i*n+k]/a[k*n+k];
+
+                a[i*n+k] = f;
+
+                for c in k+1..n { a[i*n+c] -= f*a[k*n+c]; }
+            }
+        }
+
+        Self { n: n, a: a, pivots: pivots }
+    }
+
+    pub(crate) fn solve(&self, b: &mut [f64]) {
+        let (n, a) = (self.n, &self.a);
+
+        for (k, &p) in self.pivots.iter().enumerate() { b.swap(k, p); }
+
+        for i in 0..n {
+            for c in 0..i { b[i] -= a[i*n+c]*b[c]; }
+        }
+
+        for i in (0..n).rev() {
+            for c in i+1..n { b[i] -= a[i*n+c]*b[c]; }
+
+            b[i] /= a[i*n+i];
+        }
+    }
+}
+
+
+
+// One piece of conducting surface.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Electrode {
+    pub outline: Outline,
+    pub condition: Condition
+}
+
+// A set of conductors, whose surface charge spreads itself so that each surface is all at
+// one potential. Every surface is split into straight panels, each a line charge of uniform
+// density, and the charge on each is found by requiring the potential at the middle of
+// every panel of an electrode to be the same (a boundary element method). Sharp corners draw
+// in more charge, and a closed electrode shields its inside from outside fields.
+//
+// Electrodes which affect each other strongly, such as the plates of a capacitor, must be
+// part of the same conductor so that they are solved together. Separate conductors only
+// see each other's charge through induce, and passing back and forth between them does not
+// settle when the coupling is strong.
+//
+// Conductors are fixed in place. Their charge is only correct once induce has been called
+// with the field of everything else.
+pub struct Conductor {
+    electrodes: Vec<Electrode>,
+    // Panels asked for per electrode
+    resolution: usize,
+    ends: Vec<(Vec2, Vec2)>,
+    midpoints: Vec<Vec2>,
+    // Electrode each panel belongs to
+    owners: Vec<usize>,
+    // Variable holding the potential of each floating electrode, after the panel charges
+    unknowns: Vec<Option<usize>>,
+    lu: Lu,
+    panels: Vec<LineCharge>,
+    charges: Vec<f32>,
+    potentials: Vec<f32>
+}
+
+impl Conductor {
+    // Each outline is split into roughly the given number of panels, more along longer
+    // edges. A polygon needs at least three corners and a circle a positive radius.
+    pub fn new(electrodes: Vec<Electrode>, panels: usize) -> Self {
+        let mut ends = Vec::new();
+        let mut owners = Vec::new();
+
+        for (e, electrode) in electrodes.iter().enumerate() {
+            let split = split(&electrode.outline, panels.max(3));
+
+            owners.extend(std::iter::repeat_n(e, split.len()));
+            ends.extend(split);
+        }
+
+        let n = ends.len();
+        let midpoints: Vec<Vec2> = ends.iter().map(|(a, b)| (*a+*b)/2.).collect();
+        let units: Vec<LineCharge> = ends.iter()
+                                         .map(|(a, b)| LineCharge::new(*a, *b, 1.))
+                                         .collect();
+        let mut unknowns = Vec::with_capacity(electrodes.len());
+        let mut size = n;
+
+        for electrode in &electrodes {
+            if let Condition::Floating(_) = electrode.condition {
+                unknowns.push(Some(size));
+                size += 1;
+            }
+            else { unknowns.push(None); }
+        }
+
+        // Potential at each midpoint from unit charge on each panel. Each floating electrode
+        // adds its unknown potential as an extra variable, with its total charge as an extra
+        // equation.
+        let mut a = vec![0.; size*size];
+
+        for (i, m) in midpoints.iter().enumerate() {
+            for (k, unit) in units.iter().enumerate() {
+                a[i*size+k] = unit.potential(*m) as f64;
+            }
+
+            if let Some(v) = unknowns[owners[i]] {
+                a[i*size+v] = -1.;
+                a[v*size+i] = 1.;
+            }
+        }
+
+        let potentials = vec![0.; electrodes.len()];
+        let mut conductor = Self {
+            electrodes: electrodes,
+            resolution: panels,
+            ends: ends,
+            midpoints: midpoints,
+            owners: owners,
+            unknowns: unknowns,
+            lu: Lu::new(a, size),
+            panels: Vec::new(),
+            charges: vec![0.; n],
+            potentials: potentials
+        };
+
+        conductor.induce(&NoField);
+        conductor
+    }
+
+    pub fn electrodes(&self) -> &[Electrode] {
+        &self.electrodes
+    }
+
+    // The potential of each electrode, which is the set potential unless floating.
+    pub fn potentials(&self) -> &[f32] {
+        &self.potentials
+    }
+
+    // Charge per unit length on each panel, along with the panel's ends.
+    pub fn surface_charge(&self) -> Vec<(Vec2, Vec2, f32)> {
+        self.ends.iter()
+                 .zip(&self.charges)
+                 .map(|((a, b), q)| (*a, *b, q/a.distance(*b)))
+                 .collect()
+    }
+}
+
+// Panels around an outline.
+pub(crate) fn split(outline: &Outline, panels: usize) -> Vec<(Vec2, Vec2)> {
+    let points = outline.points(panels);
+
+    if let Outline::Circle { .. } = outline {
+        return points.windows(2).map(|w| (w[0], w[1])).collect();
+    }
+
+    let spacing = outline.perimeter()/panels as f32;
+    let mut ends = Vec::with_capacity(panels);
+
+    for w in points.windows(2) {
+        let pieces = (w[0].distance(w[1])/spacing).round().max(1.) as usize;
+
+        for i in 0..pieces {
+            let t0 = i as f32/pieces as f32;
+            let t1 = (i+1) as f32/pieces as f32;
+
+            ends.push((w[0].lerp(w[1], t0), w[0].lerp(w[1], t1)));
+        }
+    }
+
+    ends
+}
+
+pub(crate) struct NoField;
+
+impl Field for NoField {
+    fn e_field(&self, _pos: Vec2) -> Vec2 { Vec2::ZERO }
+    fn potential(&self, _pos: Vec2) -> f32 { 0. }
+}
+
+impl Field for Conductor {
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        self.panels.iter().fold(Vec2::ZERO, |acc, p| acc+p.e_field(pos))
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        self.panels.iter().map(|p| p.potential(pos)).sum()
+    }
+}
+
+impl Body for Conductor {
+    fn pos(&self) -> Vec2 {
+        self.midpoints.iter().fold(Vec2::ZERO, |acc, m| acc+*m)/self.midpoints.len() as f32
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos(), ..Kinematics::default() }
+    }
+
+    fn set_kinematics(&mut self, _k: Kinematics) {}
+    fn mass(&self) -> f32 { f32::INFINITY }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        self.panels.iter().flat_map(|p| p.charge_samples()).collect()
+    }
+
+    fn charge(&self) -> f32 {
+        self.charges.iter().sum()
+    }
+
+    // Only a single circle can be collided with.
+    fn shape(&self) -> Option<Shape> {
+        match self.electrodes.as_slice() {
+            [Electrode { outline: Outline::Circle { center, radius }, .. }] => {
+                Some(Shape::circle(*center, *radius))
+            }
+            _ => None
+        }
+    }
+
+    // Lines end on the outlines, where all of the charge is.
+    fn is_sink(&self, pos: Vec2) -> bool {
+        self.panels.iter().any(|p| p.is_sink(pos))
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn induce(&mut self, external: &dyn Field) -> f32 {
+        let n = self.midpoints.len();
+        let mut b: Vec<f64> = self.midpoints
+                                  .iter()
+                                  .zip(&self.owners)
+                                  .map(|(m, &e)| {
+                                      let set = match self.electrodes[e].condition {
+                                          Condition::Potential(v) => v,
+                                          Condition::Floating(_) => 0.
+                                      };
+
+                                      (set-external.potential(*m)) as f64
+                                  })
+                                  .collect();
+
+        for electrode in &self.electrodes {
+            if let Condition::Floating(q) = electrode.condition { b.push(q as f64); }
+        }
+
+        self.lu.solve(&mut b);
+
+        let charges: Vec<f32> = b[..n].iter().map(|q| *q as f32).collect();
+        let largest = charges.iter().fold(0., |m: f32, q| m.max(q.abs()));
+        let change = self.charges.iter()
+                                 .zip(&charges)
+                                 .fold(0., |m: f32, (a, b)| m.max((a-b).abs()));
+
+        for (e, electrode) in self.electrodes.iter().enumerate() {
+            self.potentials[e] = match (electrode.condition, self.unknowns[e]) {
+                (Condition::Potential(v), _) => v,
+                (Condition::Floating(_), Some(v)) => b[v] as f32,
+                (Condition::Floating(_), None) => unreachable!()
+            };
+        }
+
+        self.panels = self.ends.iter()
+                               .zip(&charges)
+                               .map(|((a, b), q)| LineCharge::new(*a, *b, *q))
+                               .collect();
+        self.charges = charges;
+
+        if largest > 0. { change/largest } else { 0. }
+    }
+
+    // The outlines, coloured by the sign of the charge along them.
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let largest = self.charges.iter().fold(0., |m: f32, q| m.max(q.abs())).max(1e-6);
+
+        for ((a, b), q) in self.ends.iter().zip(&self.charges) {
+            let t = q/largest;
+            let color = Color::rgb(0.5+0.5*t.max(0.), 0.5-0.5*t.abs(), 0.5+0.5*(-t).max(0.));
+
+            canvas.line(*a, *b, 2., color);
+        }
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::Conductor {
+            electrodes: self.electrodes.clone(),
+            panels: self.resolution
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use crate::bodies::{Body, Field, Kinematics, LineCharge};
+use crate::canvas::*;
+use crate::collision::Shape;
+use crate::conductor::{self, Lu, NoField, Outline};
+use crate::scene::SceneBody;
+
+
+
+// A region of uniform relative permittivity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub outline: Outline,
+    pub permittivity: f32
+}
+
+// A set of dielectric regions in vacuum. An applied field polarises each region, leaving
+// bound charge on its surface, and the field of the bound charge is what bends field lines
+// at the surface and weakens the field inside.
+//
+// Like a conductor, every surface is split into straight panels, each a line charge of
+// uniform density. The density on each is found by requiring the normal component of
+// ε_r E to be the same just inside and just outside the middle of every panel. Just either
+// side of a sheet with density σ the normal field differs from its average by ±πσ, so
+// with ε_r inside and vacuum outside
+//
+//     σ = (ε_r-1)/(π(ε_r+1)) E·n
+//
+// where E·n is the average normal field, from everything else and the other panels.
+//
+// Regions must not touch or overlap each other, and free charges should be outside them,
+// as the field of a charge inside a region is not weakened. Dielectrics are fixed in
+// place, and their bound charge is only correct once induce has been called with the field
+// of everything else.
+pub struct Dielectric {
+    regions: Vec<Region>,
+    // Panels asked for per region
+    resolution: usize,
+    ends: Vec<(Vec2, Vec2)>,
+    midpoints: Vec<Vec2>,
+    // Outward unit normal of each panel
+    normals: Vec<Vec2>,
+    // (ε_r-1)/(π(ε_r+1)) for the region each panel belongs to
+    factors: Vec<f32>,
+    lu: Lu,
+    panels: Vec<LineCharge>,
+    densities: Vec<f32>
+}
+
+impl Dielectric {
+    // The outlines are split into panels as for Conductor::new.
+    pub fn new(regions: Vec<Region>, panels: usize) -> Self {
+        let mut ends = Vec::new();
+        let mut factors = Vec::new();
+
+        for region in &regions {
+            let mut split = conductor::split(&region.outline, panels.max(3));
+            let eps = region.permittivity;
+
+            // Make every outline run anticlockwise, so that the outside is on the right
+            if signed_area(&split) < 0. {
+                split = split.into_iter().rev().map(|(a, b)| (b, a)).collect();
+            }
+
+            factors.extend(std::iter::repeat_n((eps-1.)/(PI*(eps+1.)), split.len()));
+            ends.extend(split);
+        }
+
+        let n = ends.len();
+        let midpoints: Vec<Vec2> = ends.iter().map(|(a, b)| (*a+*b)/2.).collect();
+        let normals: Vec<Vec2> = ends.iter().map(|(a, b)| -(*b-*a).perp().normalize()).collect();
+        let units: Vec<LineCharge> = ends.iter()
+                                         .map(|(a, b)| LineCharge::new(*a, *b, a.distance(*b)))
+                                         .collect();
+
+        // σ_i minus the factor times the average normal field at panel i from unit density
+        // on every other panel. A panel's own field is all in the jump, so has no average.
+        let mut a = vec![0.; n*n];
+
+        for i in 0..n {
+            for (j, unit) in units.iter().enumerate() {
+                a[i*n+j] = if i == j { 1. }
+                           else { -(factors[i]*unit.e_field(midpoints[i]).dot(normals[i])) as f64 };
+            }
+        }
+
+        let mut dielectric = Self {
+            regions: regions,
+            resolution: panels,
+            ends: ends,
+            midpoints: midpoints,
+            normals: normals,
+            factors: factors,
+            lu: Lu::new(a, n),
+            panels: Vec::new(),
+            densities: vec![0.; n]
+        };
+
+        dielectric.induce(&NoField);
+        dielectric
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    // Bound charge per unit length on each panel, along with the panel's ends.
+    pub fn surface_charge(&self) -> Vec<(Vec2, Vec2, f32)> {
+        self.ends.iter().zip(&self.densities).map(|((a, b), s)| (*a, *b, *s)).collect()
+    }
+}
+
+// Twice the area enclosed by a loop of panels, positive if anticlockwise.
+fn signed_area(ends: &[(Vec2, Vec2)]) -> f32 {
+    ends.iter().map(|(a, b)| a.perp_dot(*b)).sum()
+}
+
+impl Field for Dielectric {
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        self.panels.iter().fold(Vec2::ZERO, |acc, p| acc+p.e_field(pos))
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        self.panels.iter().map(|p| p.potential(pos)).sum()
+    }
+}
+
+impl Body for Dielectric {
+    fn pos(&self) -> Vec2 {
+        self.midpoints.iter().fold(Vec2::ZERO, |acc, m| acc+*m)/self.midpoints.len() as f32
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos(), ..Kinematics::default() }
+    }
+
+    fn set_kinematics(&mut self, _k: Kinematics) {}
+    fn mass(&self) -> f32 { f32::INFINITY }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> {
+        self.panels.iter().flat_map(|p| p.charge_samples()).collect()
+    }
+
+    // Bound charge always adds up to nothing, apart from rounding.
+    fn charge(&self) -> f32 {
+        self.panels.iter().map(|p| p.charge()).sum()
+    }
+
+    // As with conductors.
+    fn shape(&self) -> Option<Shape> {
+        match self.regions.as_slice() {
+            [Region { outline: Outline::Circle { center, radius }, .. }] => {
+                Some(Shape::circle(*center, *radius))
+            }
+            _ => None
+        }
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    fn induce(&mut self, external: &dyn Field) -> f32 {
+        let mut b: Vec<f64> = self.midpoints
+                                  .iter()
+                                  .zip(&self.normals)
+                                  .zip(&self.factors)
+                                  .map(|((m, n), f)| (f*external.e_field(*m).dot(*n)) as f64)
+                                  .collect();
+
+        self.lu.solve(&mut b);
+
+        let densities: Vec<f32> = b.iter().map(|s| *s as f32).collect();
+        let largest = densities.iter().fold(0., |m: f32, s| m.max(s.abs()));
+        let change = self.densities
+                         .iter()
+                         .zip(&densities)
+                         .fold(0., |m: f32, (a, b)| m.max((a-b).abs()));
+
+        self.panels = self.ends.iter()
+                               .zip(&densities)
+                               .map(|((a, b), s)| LineCharge::new(*a, *b, s*a.distance(*b)))
+                               .collect();
+        self.densities = densities;
+
+        if largest > 0. { change/largest } else { 0. }
+    }
+
+    // The outlines, thinner than a conductor's and coloured by the sign of the bound charge.
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let largest = self.densities.iter().fold(0., |m: f32, s| m.max(s.abs())).max(1e-6);
+
+        for ((a, b), s) in self.ends.iter().zip(&self.densities) {
+            let t = s/largest;
+            let color = Color::rgb(0.4+0.4*t.max(0.), 0.6-0.2*t.abs(), 0.4+0.4*(-t).max(0.));
+
+            canvas.line(*a, *b, 1., color);
+        }
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::Dielectric {
+            regions: self.regions.clone(),
+            panels: self.resolution
+        }
+    }
+}
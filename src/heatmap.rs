@@ -0,0 +1,193 @@
+use glam::Vec2;
+use rayon::prelude::*;
+use crate::bodies::Field;
+use crate::canvas::{Canvas, Color, Viewport};
+
+
+
+// Sample the potential at the centre of every pixel of the viewport, row by row from the
+// top left. Rows are sampled in parallel.
+pub fn sample_potential(body: &(impl Field + ?Sized), viewport: &Viewport) -> Vec<f32> {
+    (0..viewport.height).into_par_iter()
+                        .flat_map_iter(|j| (0..viewport.width).map(move |i| {
+                            let pixel = Vec2::new(i as f32+0.5, j as f32+0.5);
+
+                            body.potential(viewport.to_world(pixel))
+                        }))
+                        .collect()
+}
+
+// Draw potentials sampled over a viewport, as sample_potential gives them, with each pixel
+// of the viewport as a filled square. Used with a coarse viewport, this is a cheap heat map
+// for canvases that can't set pixels directly.
+pub fn draw_heatmap(
+    canvas: &mut dyn Canvas,
+    viewport: &Viewport,
+    values: &[f32],
+    scale: &HeatmapScale
+) {
+    let width = viewport.width as usize;
+
+    for (k, v) in values.iter().enumerate() {
+        let color = scale.color(*v);
+
+        if color.a == 0. { continue; }
+
+        let (i, j) = ((k%width) as f32, (k/width) as f32);
+        let a = viewport.to_world(Vec2::new(i, j));
+        let b = viewport.to_world(Vec2::new(i+1., j+1.));
+
+        canvas.tri([a, Vec2::new(b.x, a.y), b], color);
+        canvas.tri([a, b, Vec2::new(a.x, b.y)], color);
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Colormap {
+    // Blue below the reference potential and red above
+    #[default]
+    Diverging,
+    // Purple through green to yellow, from the bottom of the range to the top
+    Sequential,
+    // Black through red to yellow with the logarithm of the distance from the reference,
+    // which shows the structure close to the reference and far from it at once
+    LogMagnitude
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 3] = [Self::Diverging, Self::Sequential, Self::LogMagnitude];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Diverging => "Diverging",
+            Self::Sequential => "Sequential",
+            Self::LogMagnitude => "Log magnitude"
+        }
+    }
+
+    // Colour for a potential t times the range above the reference, with t in [-1, 1]. The
+    // maps are kept dark so that lines drawn on top stay visible.
+    fn color(&self, t: f32) -> Color {
+        const VIRIDIS: [[f32; 3]; 5] = [
+            [0.27, 0.00, 0.33], [0.23, 0.32, 0.55], [0.13, 0.57, 0.55], [0.37, 0.79, 0.38],
+            [0.99, 0.91, 0.14]
+        ];
+        const INFERNO: [[f32; 3]; 5] = [
+            [0.00, 0.00, 0.02], [0.34, 0.06, 0.43], [0.73, 0.21, 0.33], [0.98, 0.55, 0.04],
+            [0.99, 1.00, 0.64]
+        ];
+        // Smallest |t| on the logarithmic part of the scale is 1/DECADES
+        const DECADES: f32 = 100.;
+
+        match self {
+            Self::Diverging => Color::rgb(0.7*t.max(0.), 0., 0.7*(-t).max(0.)),
+            Self::Sequential => ramp(&VIRIDIS, (t+1.)/2.),
+            Self::LogMagnitude => ramp(&INFERNO, (1.+DECADES*t.abs()).ln()/(1.+DECADES).ln())
+        }
+    }
+}
+
+// Interpolate between evenly spaced colour stops, dimmed, at s in [0, 1].
+fn ramp(stops: &[[f32; 3]], s: f32) -> Color {
+    let x = s.clamp(0., 1.)*(stops.len()-1) as f32;
+    let i = (x as usize).min(stops.len()-2);
+    let f = x-i as f32;
+    let c = |k: usize| 0.7*(stops[i][k]+f*(stops[i+1][k]-stops[i][k]));
+
+    Color::rgb(c(0), c(1), c(2))
+}
+
+// How a heat map is coloured.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct HeatmapStyle {
+    pub colormap: Colormap,
+    // Number of flat bands to split the range into, rather than shading smoothly
+    pub bands: Option<usize>,
+    // Potential at the middle of the range, or None to use the median of the samples
+    pub reference: Option<f32>
+}
+
+// Most levels HeatmapScale::levels_every will give.
+pub const MAX_LEVELS: usize = 1000;
+
+// Maps potentials onto colours, over a range either side of a reference potential.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeatmapScale {
+    pub reference: f32,
+    pub range: f32,
+    pub style: HeatmapStyle
+}
+
+impl HeatmapScale {
+    // Fit the scale to a set of samples. Potentials run off to infinity at point charges,
+    // so unless the style fixes it the reference is the median, and the range covers 95% of
+    // the finite samples, rather than using the extremes.
+    pub fn fit(values: &[f32], style: HeatmapStyle) -> Self {
+        let mut finite: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
+
+        if finite.is_empty() {
+            return Self { reference: style.reference.unwrap_or(0.), range: 1., style: style };
+        }
+
+        let reference = style.reference.unwrap_or_else(|| {
+            let mid = finite.len()/2;
+
+            *finite.select_nth_unstable_by(mid, f32::total_cmp).1
+        });
+        let mut deviations: Vec<f32> = finite.iter().map(|v| (v-reference).abs()).collect();
+        let upper = (deviations.len()-1)*95/100;
+        let range = *deviations.select_nth_unstable_by(upper, f32::total_cmp).1;
+
+        Self { reference: reference, range: if range > 0. { range } else { 1. }, style: style }
+    }
+
+    // Potentials beyond the range, including infinities, take the colour at its end. NaN
+    // potentials, where there is no field at all, are left transparent.
+    pub fn color(&self, value: f32) -> Color {
+        if value.is_nan() { return Color::rgba(0., 0., 0., 0.); }
+
+        let mut t = ((value-self.reference)/self.range).clamp(-1., 1.);
+
+        // Every potential in a band takes the colour at the middle of the band
+        if let Some(n) = self.style.bands.filter(|n| *n > 0) {
+            let band = ((t+1.)/2.*n as f32).floor().min(n as f32-1.);
+
+            t = (band+0.5)/n as f32*2.-1.;
+        }
+
+        self.style.colormap.color(t)
+    }
+
+    // Evenly spaced potentials across the range, at the middle of each of count equal parts.
+    pub fn levels(&self, count: usize) -> Vec<f32> {
+        (0..count).map(|i| self.reference+self.range*((i as f32+0.5)/count as f32*2.-1.))
+                  .collect()
+    }
+
+    // Every multiple of step across the range, for potentials a fixed step apart. Steps so
+    // small that they would give more than MAX_LEVELS are refused rather than left to bury
+    // the picture in lines.
+    pub fn levels_every(&self, step: f32) -> Result<Vec<f32>, String> {
+        if step <= 0. || step.is_nan() { return Err("level step must be positive".to_string()); }
+
+        let lo = ((self.reference-self.range)/step).ceil() as i64;
+        let hi = ((self.reference+self.range)/step).floor() as i64;
+        let count = hi.saturating_sub(lo).saturating_add(1).max(0);
+
+        if count > MAX_LEVELS as i64 {
+            return Err(format!(
+                "a step of {} gives {} levels across the range, more than the limit of {}",
+                step, count, MAX_LEVELS
+            ));
+        }
+
+        Ok((lo..=hi).map(|k| k as f32*step).collect())
+    }
+
+    // Evenly spaced potentials across the range, with their colours, for a colour bar.
+    pub fn legend(&self, samples: usize) -> Vec<(f32, Color)> {
+        self.levels(samples).into_iter().map(|v| (v, self.color(v))).collect()
+    }
+}
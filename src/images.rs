@@ -0,0 +1,304 @@
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use crate::bodies::*;
+use crate::canvas::*;
+use crate::conductor::Condition;
+use crate::quadtree::Quadtree;
+
+
+
+// A body's charge as an image boundary sees it, imaged as points or, where that's better, as
+// a whole mirror image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageSource {
+    Point { pos: Vec2, charge: f32 },
+    Circle { pos: Vec2, radius: f32, charge: f32 },
+    // A uniformly charged line segment
+    Segment { start: Vec2, end: Vec2, charge: f32 }
+}
+
+
+
+// A conducting boundary handled by the method of images. Bodies must stay in the physical
+// region, on the side of the line the normal points to or outside the circle, and the
+// field there is that of the bodies together with image charges beyond the boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImageBoundary {
+    // A grounded line through a point
+    Line { point: Vec2, normal: Vec2 },
+    Circle { center: Vec2, radius: f32, condition: Condition }
+}
+
+// Half the length of line boundaries drawn, in world units
+const LINE_EXTENT: f32 = 2000.;
+// Points along the boundary at which the induced charge is found for drawing
+pub const DRAWN_SAMPLES: usize = 400;
+
+impl ImageBoundary {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        match *self {
+            Self::Line { point, normal } => (pos-point).dot(normal) > 0.,
+            Self::Circle { center, radius, .. } => pos.distance_squared(center) > radius*radius
+        }
+    }
+
+    // Unit normal pointing into the physical region at a point on the boundary.
+    fn normal(&self, pos: Vec2) -> Vec2 {
+        match *self {
+            Self::Line { normal, .. } => normal.normalize(),
+            Self::Circle { center, .. } => (pos-center).normalize()
+        }
+    }
+
+    // Image charges for the image sources of every body. Bodies without any have no image,
+    // which is why scenes with a boundary can't have them.
+    pub fn images(&self, bodies: &[Box<dyn Body>]) -> Vec<Box<dyn Body>> {
+        let mut images = Vec::new();
+        let mut points = Vec::new();
+
+        for source in bodies.iter().filter_map(|b| b.image_sources()).flatten() {
+            self.collect(source, &mut images, &mut points);
+        }
+
+        match *self {
+            Self::Line { .. } => {
+                for (p, q) in points { images.push(image_charge(self.reflect(p), -q)); }
+            }
+            // Kelvin's image of each charge, which makes the circle an equipotential, and then
+            // a charge at the centre to set that potential or the total charge
+            Self::Circle { center, radius, condition } => {
+                let mut center_charge = 0.;
+                let mut potential = 0.;
+
+                for (p, q) in points {
+                    let r = p-center;
+                    let d = r.length();
+
+                    images.push(image_charge(center+r*radius*radius/(d*d), -q));
+                    potential += q*(radius/d).ln();
+                    center_charge += q;
+                }
+
+                center_charge = match condition {
+                    Condition::Potential(v) => (potential-v)/radius.ln(),
+                    Condition::Floating(q) => q+center_charge
+                };
+
+                images.push(image_charge(center, center_charge));
+            }
+        }
+
+        images
+    }
+
+    // Gather the charges to be imaged as points, pushing any images which are better made as
+    // whole bodies straight onto images.
+    fn collect(&self, source: ImageSource, images: &mut Vec<Box<dyn Body>>,
+               points: &mut Vec<(Vec2, f32)>) {
+        match source {
+            ImageSource::Point { pos, charge } => points.push((pos, charge)),
+            // Outside itself a circle charge looks like a point charge, and its image lies
+            // beyond the boundary, so only the mirror image in a line needs its size
+            ImageSource::Circle { pos, radius, charge } => {
+                if let Self::Line { .. } = self {
+                    images.push(Box::new(CircleCharge {
+                        charge: -charge,
+                        mass: f32::INFINITY,
+                        radius: radius,
+                        pos: self.reflect(pos),
+                        vel: Vec2::ZERO
+                    }));
+                }
+                else { points.push((pos, charge)); }
+            }
+            // A line's mirror image is a line, but its image in a circle is a curved and
+            // unevenly charged arc, which is approximated by imaging its quadrature points
+            ImageSource::Segment { start, end, charge } => {
+                if let Self::Line { .. } = self {
+                    let (start, end) = (self.reflect(start), self.reflect(end));
+
+                    images.push(Box::new(LineCharge::new(start, end, -charge)));
+                }
+                else {
+                    points.extend(LineCharge::new(start, end, charge).charge_samples());
+                }
+            }
+        }
+    }
+
+    // Mirror image of a point in a line boundary.
+    fn reflect(&self, pos: Vec2) -> Vec2 {
+        match *self {
+            Self::Line { point, normal } => {
+                let n = normal.normalize();
+
+                pos-2.*(pos-point).dot(n)*n
+            }
+            Self::Circle { .. } => pos
+        }
+    }
+
+    // Induced surface charge per unit length at points along the boundary, from the jump in
+    // the normal field across it (2πσ, with no field inside the conductor).
+    pub fn surface_charge(&self, bodies: &[Box<dyn Body>], samples: usize) -> Vec<(Vec2, f32)> {
+        let field = ImagedField::new(bodies, Some(self));
+
+        self.surface_points(samples)
+            .into_iter()
+            .map(|p| (p, field.total_e_field(p).dot(self.normal(p))/TAU))
+            .collect()
+    }
+
+    // Total charge induced on the boundary, or on the stretch of a line that is drawn.
+    pub fn induced_charge(&self, bodies: &[Box<dyn Body>]) -> f32 {
+        self.surface_charge(bodies, 4000)
+            .windows(2)
+            .map(|w| 0.5*(w[0].1+w[1].1)*w[0].0.distance(w[1].0))
+            .sum()
+    }
+
+    // Distance along the boundary of each of the points surface_charge samples, for plotting
+    // the charge against: from the point a line passes through, along its normal turned a
+    // quarter anticlockwise, or anticlockwise round a circle from its rightmost point.
+    pub fn sample_distances(&self, samples: usize) -> Vec<f32> {
+        let length = match *self {
+            Self::Line { .. } => 2.*LINE_EXTENT,
+            Self::Circle { radius, .. } => TAU*radius
+        };
+        let start = if let Self::Line { .. } = self { -LINE_EXTENT } else { 0. };
+
+        (0..samples).map(|i| start+length*sample_fraction(i, samples)).collect()
+    }
+
+    // Evenly spaced points along the boundary, over a long stretch of a line.
+    fn surface_points(&self, samples: usize) -> Vec<Vec2> {
+        let t = |i: usize| sample_fraction(i, samples);
+
+        match *self {
+            Self::Line { point, normal } => {
+                let along = normal.normalize().perp()*LINE_EXTENT;
+
+                (0..samples).map(|i| point-along+2.*along*t(i)).collect()
+            }
+            Self::Circle { center, radius, .. } => {
+                (0..samples).map(|i| {
+                                let theta = TAU*t(i);
+
+                                center+radius*Vec2::new(theta.cos(), theta.sin())
+                            })
+                            .collect()
+            }
+        }
+    }
+
+    // Shade the region beyond the boundary and draw the boundary itself, coloured by the
+    // sign of the charge induced along it, as surface_charge gives it.
+    pub fn draw(&self, canvas: &mut dyn Canvas, charge: &[(Vec2, f32)]) {
+        const SHADE: Color = Color::rgba(0.3, 0.3, 0.3, 0.5);
+
+        match *self {
+            Self::Line { point, normal } => {
+                let (n, along) = (normal.normalize(), normal.normalize().perp()*LINE_EXTENT);
+                let far = point-n*LINE_EXTENT;
+
+                canvas.tri([point-along, point+along, far+along], SHADE);
+                canvas.tri([point-along, far+along, far-along], SHADE);
+            }
+            Self::Circle { center, radius, .. } => canvas.ellipse(center, radius, SHADE)
+        }
+
+        let largest = charge.iter().fold(0., |m: f32, (_, s)| m.max(s.abs())).max(1e-6);
+
+        for w in charge.windows(2) {
+            let t = (w[0].1+w[1].1)/(2.*largest);
+            let color = Color::rgb(0.5+0.5*t.max(0.), 0.5-0.5*t.abs(), 0.5+0.5*(-t).max(0.));
+
+            canvas.line(w[0].0, w[1].0, 2., color);
+        }
+    }
+}
+
+// How far the ith of some samples evenly spaced along the boundary is from the first, as a
+// fraction of the way to the last.
+fn sample_fraction(i: usize, samples: usize) -> f32 {
+    i as f32/(samples-1).max(1) as f32
+}
+
+// A fixed point charge standing in for charge induced on the boundary.
+fn image_charge(pos: Vec2, charge: f32) -> Box<dyn Body> {
+    Box::new(PointCharge { charge: charge, mass: f32::INFINITY, pos: pos, vel: Vec2::ZERO })
+}
+
+
+
+// The field of some bodies together with their images in a boundary, if there is one.
+// Beyond the boundary the field and potential are NaN, which stops any line traced into it
+// and leaves it blank in heat maps.
+pub struct ImagedField<'a> {
+    bodies: &'a [Box<dyn Body>],
+    tree: Option<Quadtree<'a>>,
+    images: Vec<Box<dyn Body>>,
+    boundary: Option<&'a ImageBoundary>
+}
+
+impl<'a> ImagedField<'a> {
+    pub fn new(bodies: &'a [Box<dyn Body>], boundary: Option<&'a ImageBoundary>) -> Self {
+        let images = boundary.map(|b| b.images(bodies)).unwrap_or_default();
+
+        Self { bodies: bodies, tree: None, images: images, boundary: boundary }
+    }
+
+    // Sum the bodies with a Barnes-Hut tree of the given opening angle, if any, rather than
+    // exactly. Images are always summed exactly.
+    pub fn with_opening_angle(mut self, opening_angle: Option<f32>) -> Self {
+        self.tree = opening_angle.map(|theta| Quadtree::new(self.bodies, theta));
+        self
+    }
+
+    fn bodies_e_field(&self, pos: Vec2) -> Vec2 {
+        match &self.tree {
+            Some(tree) => tree.e_field(pos),
+            None => self.bodies.e_field(pos)
+        }
+    }
+
+    fn bodies_potential(&self, pos: Vec2) -> f32 {
+        match &self.tree {
+            Some(tree) => tree.potential(pos),
+            None => self.bodies.potential(pos)
+        }
+    }
+
+    fn outside(&self, pos: Vec2) -> bool {
+        self.boundary.is_some_and(|b| !b.contains(pos))
+    }
+
+    // The field regardless of which side of the boundary pos is on.
+    fn total_e_field(&self, pos: Vec2) -> Vec2 {
+        self.bodies_e_field(pos)+self.images.e_field(pos)
+    }
+}
+
+impl<'a> Field for ImagedField<'a> {
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        if self.outside(pos) { Vec2::splat(f32::NAN) } else { self.total_e_field(pos) }
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        if self.outside(pos) { f32::NAN }
+        else { self.bodies_potential(pos)+self.images.potential(pos) }
+    }
+
+    fn b_field(&self, pos: Vec2) -> Vec3 {
+        if self.outside(pos) { Vec3::splat(f32::NAN) }
+        else if let Some(tree) = &self.tree { tree.b_field(pos) }
+        else { self.bodies.b_field(pos) }
+    }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        if self.outside(pos) { f32::NAN }
+        else if let Some(tree) = &self.tree { tree.vector_potential(pos) }
+        else { self.bodies.vector_potential(pos) }
+    }
+}
@@ -0,0 +1,295 @@
+use glam::Vec2;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use crate::canvas::Viewport;
+use crate::images::ImagedField;
+use crate::lic::{Lic, LicStyle};
+use crate::scene::Scene;
+use crate::util::{self, LineEnd};
+
+
+
+enum Message {
+    // Number of field lines that will be traced
+    Total(usize),
+    // A finished line, its place among the others and why each end stopped
+    Line(usize, Vec<Vec2>, [LineEnd; 2]),
+    Finished,
+    Failed(String)
+}
+
+// Where a TraceJob starts its field lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seeding {
+    // Crossing the scene's isopotentials, as util::trace_field_lines does
+    Isopotentials,
+    // Straight from the charges, as util::auto_field_lines does
+    Charges
+}
+
+// Field lines traced on a worker thread, so that a long trace doesn't hold up the caller.
+// The job works on its own copy of a scene, rebuilding the bodies from it, and traces the
+// same field lines as util::trace_field_lines or util::auto_field_lines. Lines can be
+// collected as they are finished by polling the job.
+//
+// Dropping the job cancels it. The worker stops after the lines it is tracing at the time.
+pub struct TraceJob {
+    scene: Scene,
+    seeding: Seeding,
+    receiver: Receiver<Message>,
+    cancelled: Arc<AtomicBool>,
+    // Finished lines, kept in the order trace_field_lines would give them
+    lines: Vec<(usize, Vec<Vec2>, [LineEnd; 2])>,
+    total: Option<usize>,
+    finished: bool,
+    error: Option<String>
+}
+
+impl TraceJob {
+    pub fn start(scene: Scene, flux_step: f32, seeding: Seeding) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let job = Self {
+            scene: scene.clone(),
+            seeding: seeding,
+            receiver: receiver,
+            cancelled: cancelled.clone(),
+            lines: Vec::new(),
+            total: None,
+            finished: false,
+            error: None
+        };
+
+        thread::spawn(move || trace(scene, flux_step, seeding, sender, cancelled));
+
+        job
+    }
+
+    // Collect any lines finished since the last poll. Returns whether there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        for message in self.receiver.try_iter() {
+            match message {
+                Message::Total(n) => self.total = Some(n),
+                Message::Line(i, points, ends) => {
+                    let at = self.lines.partition_point(|(j, _, _)| *j < i);
+
+                    self.lines.insert(at, (i, points, ends));
+                    changed = true;
+                }
+                Message::Finished => self.finished = true,
+                Message::Failed(e) => {
+                    self.error = Some(e);
+                    self.finished = true;
+                }
+            }
+        }
+
+        changed
+    }
+
+    // The lines finished so far, in their final order.
+    pub fn lines(&self) -> Vec<Vec<Vec2>> {
+        self.lines.iter().map(|(_, points, _)| points.clone()).collect()
+    }
+
+    // Why each end of the lines finished so far stopped, in the same order as the lines.
+    pub fn ends(&self) -> Vec<[LineEnd; 2]> {
+        self.lines.iter().map(|(_, _, ends)| *ends).collect()
+    }
+
+    pub fn seeding(&self) -> Seeding {
+        self.seeding
+    }
+
+    // Fraction of the lines finished, which is 0 until the number of lines is known.
+    pub fn progress(&self) -> f32 {
+        match self.total {
+            Some(0) => 1.,
+            Some(n) => self.lines.len() as f32/n as f32,
+            None => 0.
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    // Whether the job was started from a scene whose lines would come out differently, with
+    // other bodies, boundary or opening angle, or other isopotentials if it starts from them.
+    pub fn is_stale(&self, scene: &Scene) -> bool {
+        let seeds_moved = self.seeding == Seeding::Isopotentials
+                          && self.scene.isopotentials != scene.isopotentials;
+
+        !self.scene.same_field(scene) || seeds_moved
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for TraceJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// The work done on the worker thread. Sending fails once the job has been dropped, which
+// is harmless as the cancel flag is set by then too.
+fn trace(
+    scene: Scene,
+    flux_step: f32,
+    seeding: Seeding,
+    sender: Sender<Message>,
+    cancelled: Arc<AtomicBool>
+) {
+    let bodies = match scene.bodies() {
+        Ok(bodies) => bodies,
+        Err(e) => {
+            let _ = sender.send(Message::Failed(e.to_string()));
+            return;
+        }
+    };
+    let field = ImagedField::new(&bodies, scene.boundary.as_ref())
+                            .with_opening_angle(scene.opening_angle);
+    // Trace lines from each origin, numbering them on from first and sending each one as
+    // it is finished. Lines are also returned, or None if the job is cancelled part way.
+    let trace_from = |origins: &[Vec2], first: usize| {
+        let lines: Vec<Option<(Vec<Vec2>, [LineEnd; 2])>> = origins
+            .par_iter()
+            .enumerate()
+            .map_with(sender.clone(), |sender, (i, r0)| {
+                if cancelled.load(Ordering::Relaxed) { return None; }
+
+                let (points, ends) = util::trace_field_line(&field, &bodies, *r0);
+
+                let _ = sender.send(Message::Line(first+i, points.clone(), ends));
+                Some((points, ends))
+            })
+            .collect();
+
+        lines.into_iter().collect::<Option<Vec<_>>>()
+    };
+
+    if cancelled.load(Ordering::Relaxed) { return; }
+
+    match seeding {
+        Seeding::Isopotentials => {
+            let origins = util::field_line_origins(&field, &scene.isopotentials, flux_step);
+            let _ = sender.send(Message::Total(origins.len()));
+
+            if trace_from(&origins, 0).is_none() { return; }
+        }
+        // The number of lines from the negative charges is only known once the lines from
+        // the positive ones are done, so until then the total counts every seed
+        Seeding::Charges => {
+            let (sources, sinks) = util::charge_seeds(&bodies, flux_step);
+            let _ = sender.send(Message::Total(sources.len()+sinks.len()));
+            let lines = match trace_from(&sources, 0) {
+                Some(lines) => lines,
+                None => return
+            };
+            let rest = util::unreached_seeds(&bodies, &sinks, &lines);
+            let _ = sender.send(Message::Total(sources.len()+rest.len()));
+
+            if trace_from(&rest, sources.len()).is_none() { return; }
+        }
+    }
+
+    let _ = sender.send(Message::Finished);
+}
+
+
+
+// A LIC texture computed on a worker thread from its own copy of a scene, in the same way
+// as a TraceJob. The job can be kept once it has finished, to tell when the scene has moved
+// on and the texture needs computing again.
+pub struct LicJob {
+    scene: Scene,
+    viewport: Viewport,
+    style: LicStyle,
+    receiver: Receiver<Result<Lic, String>>,
+    cancelled: Arc<AtomicBool>,
+    rows: Arc<AtomicUsize>,
+    finished: bool
+}
+
+impl LicJob {
+    pub fn start(scene: Scene, viewport: Viewport, style: LicStyle) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let rows = Arc::new(AtomicUsize::new(0));
+        let job = Self {
+            scene: scene.clone(),
+            viewport: viewport,
+            style: style,
+            receiver: receiver,
+            cancelled: cancelled.clone(),
+            rows: rows.clone(),
+            finished: false
+        };
+
+        thread::spawn(move || {
+            let bodies = match scene.bodies() {
+                Ok(bodies) => bodies,
+                Err(e) => {
+                    let _ = sender.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let field = ImagedField::new(&bodies, scene.boundary.as_ref())
+                                    .with_opening_angle(scene.opening_angle);
+
+            if let Some(lic) = Lic::compute(&field, &viewport, style, &cancelled, &rows) {
+                let _ = sender.send(Ok(lic));
+            }
+        });
+
+        job
+    }
+
+    // The texture, or the reason it couldn't be made, once it is finished. This is only
+    // returned once.
+    pub fn poll(&mut self) -> Option<Result<Lic, String>> {
+        let result = self.receiver.try_recv().ok();
+
+        if result.is_some() { self.finished = true; }
+
+        result
+    }
+
+    pub fn progress(&self) -> f32 {
+        let total = Lic::total_rows(&self.viewport, self.style).max(1);
+
+        self.rows.load(Ordering::Relaxed) as f32/total as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Whether the texture would come out differently for a scene with another field, a
+    // different viewport or style.
+    pub fn is_stale(&self, scene: &Scene, viewport: &Viewport, style: LicStyle) -> bool {
+        !self.scene.same_field(scene) || self.viewport != *viewport || self.style != style
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for LicJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,21 @@
+#![allow(clippy::redundant_field_names)]
+
+pub mod bodies;
+pub mod canvas;
+pub mod collision;
+pub mod conductor;
+pub mod dielectric;
+pub mod heatmap;
+pub mod images;
+pub mod job;
+pub mod lic;
+pub mod poisson;
+pub mod quadtree;
+pub mod quiver;
+pub mod raster;
+pub mod scene;
+pub mod simulation;
+pub mod svg;
+pub mod util;
+
+pub use glam::{Mat2, Vec2};
@@ -0,0 +1,216 @@
+use glam::Vec2;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::bodies::Field;
+use crate::canvas::{Color, Viewport};
+use crate::heatmap::{Colormap, HeatmapScale, HeatmapStyle};
+use crate::util::hash_unit;
+
+
+
+// What colours the texture, which is otherwise grey.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LicTint {
+    #[default]
+    None,
+    // The logarithm of the field strength
+    Strength,
+    Potential
+}
+
+impl LicTint {
+    pub const ALL: [LicTint; 3] = [Self::None, Self::Strength, Self::Potential];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Strength => "Field strength",
+            Self::Potential => "Potential"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LicStyle {
+    // Pixels followed along the streamline either way from each pixel. Longer streaks show
+    // the field's shape more clearly but blur small features.
+    pub length: f32,
+    pub tint: LicTint
+}
+
+impl Default for LicStyle {
+    fn default() -> Self {
+        Self { length: 20., tint: LicTint::None }
+    }
+}
+
+
+
+// A line integral convolution texture of the electric field over a viewport. Every pixel is
+// the average of white noise along the streamline of the field through it, so the noise is
+// smeared into streaks following the field lines everywhere at once. Only the direction of
+// the field matters, so the texture looks the same in weak fields as in strong ones.
+pub struct Lic {
+    width: u32,
+    height: u32,
+    // Row by row from the top left
+    pixels: Vec<Color>
+}
+
+impl Lic {
+    pub fn new(body: &(impl Field + ?Sized), viewport: &Viewport, style: LicStyle) -> Self {
+        let (cancelled, rows) = (AtomicBool::new(false), AtomicUsize::new(0));
+
+        Self::compute(body, viewport, style, &cancelled, &rows).unwrap()
+    }
+
+    // Compute the texture, counting each row finished in rows, which ends up at total_rows.
+    // Returns None soon after cancelled is set.
+    pub fn compute(
+        body: &(impl Field + ?Sized),
+        viewport: &Viewport,
+        style: LicStyle,
+        cancelled: &AtomicBool,
+        rows: &AtomicUsize
+    ) -> Option<Self> {
+        let (width, height) = (viewport.width as usize, viewport.height as usize);
+        let pixel = |i: usize, j: usize| viewport.to_world(Vec2::new(i as f32+0.5, j as f32+0.5));
+        let fields = each_pixel(viewport, cancelled, rows, |i, j| body.e_field(pixel(i, j)))?;
+        // Direction of the field in pixel coordinates, which have y pointing down
+        let directions: Vec<Vec2> = fields.iter()
+                                          .map(|e| Vec2::new(e.x, -e.y).normalize_or_zero())
+                                          .map(|d| if d.is_finite() { d } else { Vec2::ZERO })
+                                          .collect();
+        let noise: Vec<f32> = (0..width*height)
+            .map(|k| hash_unit((k%width) as i64, (k/width) as i64, 3))
+            .collect();
+        let index = |p: Vec2| {
+            let inside = p.x >= 0. && p.y >= 0. && p.x < width as f32 && p.y < height as f32;
+
+            if inside { Some(p.y as usize*width+p.x as usize) } else { None }
+        };
+        let direction = |p: Vec2| index(p).map_or(Vec2::ZERO, |k| directions[k]);
+        let steps = style.length.max(1.) as usize;
+
+        // Follow the streamline a pixel at a time with the midpoint method, both ways,
+        // averaging the noise under it, until it leaves the viewport or reaches a point
+        // with no direction
+        let convolved = each_pixel(viewport, cancelled, rows, |i, j| {
+            let start = Vec2::new(i as f32+0.5, j as f32+0.5);
+            let (mut sum, mut count) = (noise[j*width+i], 1.);
+
+            for sign in [1., -1.] {
+                let mut p = start;
+
+                for _ in 0..steps {
+                    let d = direction(p+0.5*sign*direction(p));
+
+                    if d == Vec2::ZERO { break; }
+
+                    p += sign*d;
+
+                    match index(p) {
+                        Some(k) if directions[k] != Vec2::ZERO => {
+                            sum += noise[k];
+                            count += 1.;
+                        }
+                        _ => break
+                    }
+                }
+            }
+
+            sum/count
+        })?;
+
+        // Averaging flattens the noise towards its mean, so stretch it back out
+        let n = convolved.len().max(1) as f32;
+        let mean = convolved.iter().sum::<f32>()/n;
+        let deviation = (convolved.iter().map(|v| (v-mean)*(v-mean)).sum::<f32>()/n).sqrt();
+        let brightness = |v: f32| (0.5+(v-mean)/(4.*deviation.max(1e-6))).clamp(0., 1.);
+
+        let tints = match style.tint {
+            LicTint::None => None,
+            LicTint::Strength => Some(fields.iter().map(|e| e.length().ln()).collect()),
+            LicTint::Potential => {
+                Some(each_pixel(viewport, cancelled, rows, |i, j| body.potential(pixel(i, j)))?)
+            }
+        };
+        let tint = tints.map(|t: Vec<f32>| {
+            let style = HeatmapStyle { colormap: Colormap::Sequential, ..HeatmapStyle::default() };
+            let scale = HeatmapScale::fit(&t, style);
+
+            t.into_iter().map(|v| brighten(scale.color(v))).collect::<Vec<Color>>()
+        });
+
+        // Beyond the edge of the field, where it is NaN, is left transparent
+        let pixels = (0..width*height)
+            .map(|k| {
+                if fields[k].is_nan() { return Color::rgba(0., 0., 0., 0.); }
+
+                let b = brightness(convolved[k]);
+                let c = tint.as_ref().map_or(Color::rgb(1., 1., 1.), |t| t[k]);
+
+                Color::rgb(b*c.r, b*c.g, b*c.b)
+            })
+            .collect();
+
+        Some(Self { width: viewport.width, height: viewport.height, pixels: pixels })
+    }
+
+    // Rows counted by compute, as the field, the convolution and any potential tint are
+    // each done a row at a time.
+    pub fn total_rows(viewport: &Viewport, style: LicStyle) -> usize {
+        let passes = if style.tint == LicTint::Potential { 3 } else { 2 };
+
+        passes*viewport.height as usize
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    // The pixels as 8-bit RGBA, for handing to image libraries.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels.iter()
+                   .flat_map(|c| [c.r, c.g, c.b, c.a].map(|x| (x*255.).round() as u8))
+                   .collect()
+    }
+}
+
+// Scale a colour up so that its brightest channel is full, leaving only its hue.
+fn brighten(c: Color) -> Color {
+    let top = c.r.max(c.g).max(c.b);
+
+    if top > 0. { Color::rgb(c.r/top, c.g/top, c.b/top) } else { c }
+}
+
+// Evaluate f at every pixel of the viewport, row by row from the top left, in parallel and
+// counting rows as they are finished. Returns None if cancelled is set part way through.
+fn each_pixel<T: Send>(
+    viewport: &Viewport,
+    cancelled: &AtomicBool,
+    rows: &AtomicUsize,
+    f: impl Fn(usize, usize) -> T + Sync
+) -> Option<Vec<T>> {
+    let width = viewport.width as usize;
+    let values: Vec<T> = (0..viewport.height as usize)
+        .into_par_iter()
+        .flat_map_iter(|j| {
+            let row: Vec<T> = if cancelled.load(Ordering::Relaxed) { Vec::new() }
+                              else { (0..width).map(|i| f(i, j)).collect() };
+
+            rows.fetch_add(1, Ordering::Relaxed);
+            row
+        })
+        .collect();
+
+    if cancelled.load(Ordering::Relaxed) { None } else { Some(values) }
+}
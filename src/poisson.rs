@@ -0,0 +1,350 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+use crate::bodies::{Body, Field, Kinematics};
+use crate::canvas::*;
+use crate::collision::Shape;
+use crate::scene::SceneBody;
+
+
+
+// A rectangle of nx by ny nodes spaced evenly apart, with node (0, 0) at the origin in the
+// bottom left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Grid {
+    pub origin: Vec2,
+    pub spacing: f32,
+    pub nx: usize,
+    pub ny: usize
+}
+
+impl Grid {
+    // The grid with the given spacing from lo, reaching at least as far as hi.
+    pub fn covering(lo: Vec2, hi: Vec2, spacing: f32) -> Self {
+        let size = ((hi-lo)/spacing).ceil().max(Vec2::ONE);
+
+        Self { origin: lo, spacing: spacing, nx: size.x as usize+1, ny: size.y as usize+1 }
+    }
+
+    // Top right corner.
+    pub fn end(&self) -> Vec2 {
+        self.origin+self.spacing*Vec2::new((self.nx-1) as f32, (self.ny-1) as f32)
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        j*self.nx+i
+    }
+}
+
+// Condition on one edge of the grid. Dirichlet fixes the potential along the edge, and
+// Neumann fixes its outward normal derivative, i.e. minus the outward normal field, so
+// Neumann(0.) is an edge which no field lines cross.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Boundary {
+    Dirichlet(f32),
+    Neumann(f32)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Boundaries {
+    pub left: Boundary,
+    pub right: Boundary,
+    pub bottom: Boundary,
+    pub top: Boundary
+}
+
+impl Default for Boundaries {
+    // A grounded box
+    fn default() -> Self {
+        let b = Boundary::Dirichlet(0.);
+
+        Self { left: b, right: b, bottom: b, top: b }
+    }
+}
+
+// Largest grid solved, in nodes along either side
+pub const MAX_GRID_SIZE: usize = 2049;
+
+// Relative change in potential over a sweep at which successive over-relaxation is taken to
+// have converged. Rounding keeps the change from settling much below 1e-6.
+const TOLERANCE: f32 = 1e-5;
+
+
+
+// Spread each point charge over the four nodes around it, weighted by its distance from
+// each. Charges outside the grid are dropped.
+fn deposit(grid: &Grid, sources: &[Box<dyn Body>]) -> Vec<f32> {
+    let mut charge = vec![0.; grid.nx*grid.ny];
+
+    for (p, q) in sources.iter().flat_map(|b| b.charge_samples()) {
+        let g = (p-grid.origin)/grid.spacing;
+        let (i, j) = (g.x.floor(), g.y.floor());
+
+        if !(i >= 0. && j >= 0. && i < (grid.nx-1) as f32 && j < (grid.ny-1) as f32) {
+            continue;
+        }
+
+        let (i, j) = (i as usize, j as usize);
+        let (fx, fy) = (g.x-i as f32, g.y-j as f32);
+
+        charge[grid.index(i, j)] += q*(1.-fx)*(1.-fy);
+        charge[grid.index(i+1, j)] += q*fx*(1.-fy);
+        charge[grid.index(i, j+1)] += q*(1.-fx)*fy;
+        charge[grid.index(i+1, j+1)] += q*fx*fy;
+    }
+
+    charge
+}
+
+// Check that a grid and its boundaries describe a problem which can be solved.
+pub(crate) fn check_grid(grid: &Grid, boundaries: &Boundaries) -> Result<(), String> {
+    let edges = [boundaries.left, boundaries.right, boundaries.bottom, boundaries.top];
+
+    if !(grid.spacing > 0. && grid.spacing.is_finite() && grid.origin.is_finite()) {
+        return Err("grid spacing must be positive".to_string());
+    }
+
+    if grid.nx < 3 || grid.ny < 3 || grid.nx > MAX_GRID_SIZE || grid.ny > MAX_GRID_SIZE {
+        return Err(format!(
+            "grid must have between 3 and {} nodes along each side", MAX_GRID_SIZE
+        ));
+    }
+
+    let finite = |b: &Boundary| match b {
+        Boundary::Dirichlet(v) | Boundary::Neumann(v) => v.is_finite()
+    };
+
+    if !edges.iter().all(finite) {
+        return Err("boundary values must be finite".to_string());
+    }
+
+    if !edges.iter().any(|b| matches!(b, Boundary::Dirichlet(_))) {
+        return Err("at least one edge of the grid must be Dirichlet".to_string());
+    }
+
+    Ok(())
+}
+
+// Solve for the potential of the given bodies on a grid, with ∇²V = -2πρ inside and the
+// given conditions on the edges, by red-black successive over-relaxation. Bodies contribute
+// through their charge samples, so those without charge, such as global fields, are left
+// out and should be accounted for through the boundaries instead.
+//
+// At least one edge must be Dirichlet, as otherwise the potential is only defined up to a
+// constant.
+pub fn solve(
+    grid: Grid,
+    boundaries: Boundaries,
+    sources: Vec<Box<dyn Body>>
+) -> Result<PoissonField, String> {
+    check_grid(&grid, &boundaries)?;
+
+    let (nx, ny, h) = (grid.nx, grid.ny, grid.spacing);
+    let charge = deposit(&grid, &sources);
+    let mut v = vec![0.; nx*ny];
+    let mut fixed = vec![false; nx*ny];
+    let is_neumann = |b: Boundary| matches!(b, Boundary::Neumann(_));
+
+    // Dirichlet edges are applied in order, so the later ones win at the corners
+    for (b, nodes) in [
+        (boundaries.bottom, (0..nx).map(|i| (i, 0)).collect::<Vec<_>>()),
+        (boundaries.top, (0..nx).map(|i| (i, ny-1)).collect()),
+        (boundaries.left, (0..ny).map(|j| (0, j)).collect()),
+        (boundaries.right, (0..ny).map(|j| (nx-1, j)).collect())
+    ] {
+        if let Boundary::Dirichlet(value) = b {
+            for (i, j) in nodes {
+                v[grid.index(i, j)] = value;
+                fixed[grid.index(i, j)] = true;
+            }
+        }
+    }
+
+    // The source term at each node. Nodes on a Neumann edge only own half a cell, or a
+    // quarter at a corner, so the charge deposited there is denser.
+    let source: Vec<f32> = (0..nx*ny).map(|n| {
+        let (i, j) = (n%nx, n/nx);
+        let x_edge = (i == 0 && is_neumann(boundaries.left))
+                     || (i == nx-1 && is_neumann(boundaries.right));
+        let y_edge = (j == 0 && is_neumann(boundaries.bottom))
+                     || (j == ny-1 && is_neumann(boundaries.top));
+        let area = if x_edge { 0.5 } else { 1. }*if y_edge { 0.5 } else { 1. };
+
+        TAU*charge[n]/area
+    }).collect();
+
+    // Potential just beyond the grid, mirroring the node inside to give the right slope.
+    let slope = |b: Boundary| if let Boundary::Neumann(g) = b { 2.*h*g } else { 0. };
+    let (left, right) = (slope(boundaries.left), slope(boundaries.right));
+    let (bottom, top) = (slope(boundaries.bottom), slope(boundaries.top));
+    let omega = 2./(1.+(PI/nx.max(ny) as f32).sin());
+    let max_iterations = 20*nx.max(ny);
+    let mut iterations = 0;
+
+    while iterations < max_iterations {
+        let mut max_change: f32 = 0.;
+        let mut max_v: f32 = 0.;
+
+        for colour in 0..2 {
+            for j in 0..ny {
+                for i in ((j+colour)%2..nx).step_by(2) {
+                    let n = grid.index(i, j);
+
+                    if fixed[n] { continue; }
+
+                    let west = if i > 0 { v[n-1] } else { v[n+1]+left };
+                    let east = if i < nx-1 { v[n+1] } else { v[n-1]+right };
+                    let south = if j > 0 { v[n-nx] } else { v[n+nx]+bottom };
+                    let north = if j < ny-1 { v[n+nx] } else { v[n-nx]+top };
+                    let change = omega*((west+east+south+north+source[n])/4.-v[n]);
+
+                    v[n] += change;
+                    max_change = max_change.max(change.abs());
+                    max_v = max_v.max(v[n].abs());
+                }
+            }
+        }
+
+        iterations += 1;
+
+        if !max_change.is_finite() { return Err("solution diverged".to_string()); }
+        if max_change <= TOLERANCE*max_v.max(1.) { break; }
+    }
+
+    Ok(PoissonField::new(grid, boundaries, v, sources, iterations))
+}
+
+
+
+// The solved potential, interpolated bilinearly between the nodes, and the field found from
+// its differences. Outside the grid both are NaN, which stops any line traced through it,
+// while bodies there feel nothing from it.
+//
+// This is a fixed body which feels no force. It already includes the field of the bodies it
+// was solved from, so it stands in for them rather than joining them. They are kept only for
+// their charges, which field lines end on.
+pub struct PoissonField {
+    grid: Grid,
+    boundaries: Boundaries,
+    potential: Vec<f32>,
+    field: Vec<Vec2>,
+    sources: Vec<Box<dyn Body>>,
+    iterations: usize
+}
+
+impl PoissonField {
+    fn new(
+        grid: Grid,
+        boundaries: Boundaries,
+        potential: Vec<f32>,
+        sources: Vec<Box<dyn Body>>,
+        iterations: usize
+    ) -> Self {
+        let (nx, ny, h) = (grid.nx, grid.ny, grid.spacing);
+        let mut field = Vec::with_capacity(nx*ny);
+
+        // Central differences inside, one sided on the edges
+        for j in 0..ny {
+            for i in 0..nx {
+                let (i0, i1) = (i.saturating_sub(1), (i+1).min(nx-1));
+                let (j0, j1) = (j.saturating_sub(1), (j+1).min(ny-1));
+                let dx = potential[grid.index(i1, j)]-potential[grid.index(i0, j)];
+                let dy = potential[grid.index(i, j1)]-potential[grid.index(i, j0)];
+
+                field.push(-Vec2::new(dx/((i1-i0) as f32*h), dy/((j1-j0) as f32*h)));
+            }
+        }
+
+        Self {
+            grid: grid,
+            boundaries: boundaries,
+            potential: potential,
+            field: field,
+            sources: sources,
+            iterations: iterations
+        }
+    }
+
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    // Number of sweeps taken to converge.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    // The cell containing pos and the position within it, or None outside the grid.
+    fn locate(&self, pos: Vec2) -> Option<(usize, usize, Vec2)> {
+        let g = (pos-self.grid.origin)/self.grid.spacing;
+        let (max_x, max_y) = ((self.grid.nx-1) as f32, (self.grid.ny-1) as f32);
+
+        if !(g.x >= 0. && g.y >= 0. && g.x <= max_x && g.y <= max_y) { return None; }
+
+        let (i, j) = (g.x.floor().min(max_x-1.), g.y.floor().min(max_y-1.));
+
+        Some((i as usize, j as usize, g-Vec2::new(i, j)))
+    }
+
+    fn interpolate<T>(&self, values: &[T], pos: Vec2) -> Option<T>
+    where T: Copy+std::ops::Mul<f32, Output=T>+std::ops::Add<Output=T> {
+        let (i, j, f) = self.locate(pos)?;
+        let at = |di, dj| values[self.grid.index(i+di, j+dj)];
+
+        Some(at(0, 0)*((1.-f.x)*(1.-f.y))+at(1, 0)*(f.x*(1.-f.y))
+             +at(0, 1)*((1.-f.x)*f.y)+at(1, 1)*(f.x*f.y))
+    }
+}
+
+impl Field for PoissonField {
+    fn e_field(&self, pos: Vec2) -> Vec2 {
+        self.interpolate(&self.field, pos).unwrap_or(Vec2::splat(f32::NAN))
+    }
+
+    fn potential(&self, pos: Vec2) -> f32 {
+        self.interpolate(&self.potential, pos).unwrap_or(f32::NAN)
+    }
+}
+
+impl Body for PoissonField {
+    fn pos(&self) -> Vec2 {
+        (self.grid.origin+self.grid.end())/2.
+    }
+
+    fn kinematics(&self) -> Kinematics {
+        Kinematics { pos: self.pos(), ..Kinematics::default() }
+    }
+
+    fn set_kinematics(&mut self, _k: Kinematics) {}
+    fn mass(&self) -> f32 { f32::INFINITY }
+    fn inertia(&self) -> f32 { f32::INFINITY }
+    fn charge_samples(&self) -> Vec<(Vec2, f32)> { Vec::new() }
+    fn charge(&self) -> f32 { 0. }
+    fn shape(&self) -> Option<Shape> { None }
+
+    fn is_sink(&self, pos: Vec2) -> bool {
+        self.sources.iter().any(|b| b.is_sink(pos))
+    }
+
+    fn covers(&self, pos: Vec2) -> bool {
+        self.locate(pos).is_some()
+    }
+
+    fn merge(&mut self, _other: &dyn Body) -> bool { false }
+
+    // The outline of the grid.
+    fn draw(&self, canvas: &mut dyn Canvas) {
+        let (a, b) = (self.grid.origin, self.grid.end());
+        let corners = [a, Vec2::new(b.x, a.y), b, Vec2::new(a.x, b.y), a];
+
+        canvas.polyline(&corners, 1., GREY);
+    }
+
+    fn to_scene(&self) -> SceneBody {
+        SceneBody::PoissonGrid {
+            grid: self.grid,
+            boundaries: self.boundaries,
+            sources: self.sources.iter().map(|b| b.to_scene()).collect()
+        }
+    }
+}
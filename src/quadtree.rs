@@ -0,0 +1,316 @@
+use glam::{Vec2, Vec3};
+use std::ops::Add;
+use crate::bodies::{Body, Field};
+
+
+
+// Highest multipole kept for distant groups of charge
+const ORDER: usize = 6;
+// Most bodies in a node before it is split
+const LEAF_SIZE: usize = 8;
+// Nodes this deep are never split, so that bodies in the same place don't split forever
+const MAX_DEPTH: usize = 32;
+
+// Complex arithmetic on vectors, x being the real part.
+fn mul(a: Vec2, b: Vec2) -> Vec2 {
+    Vec2::new(a.x*b.x-a.y*b.y, a.x*b.y+a.y*b.x)
+}
+
+fn conj(a: Vec2) -> Vec2 {
+    Vec2::new(a.x, -a.y)
+}
+
+// Coefficients of a multipole expansion about a centre. Treating positions as complex
+// numbers, the field of charges q_k at z_k, relative to the centre, is
+//
+//     E(z) = conj(sum_k q_k/(z-z_k)) = conj(sum_p a_p/z^(p+1))    where a_p = sum_k q_k z_k^p
+//
+// which converges for z further out than every z_k, and quickly when much further. The
+// potential follows in the same way from ln(z-z_k).
+#[derive(Clone, Copy, Debug)]
+struct Moments([Vec2; ORDER+1]);
+
+impl Moments {
+    fn new(samples: impl Iterator<Item = (Vec2, f32)>, center: Vec2) -> Self {
+        let mut a = [Vec2::ZERO; ORDER+1];
+
+        for (p, q) in samples {
+            let z = p-center;
+            let mut power = Vec2::new(q, 0.);
+
+            for a in a.iter_mut() {
+                *a += power;
+                power = mul(power, z);
+            }
+        }
+
+        Self(a)
+    }
+
+    // The field of the charges at z relative to the centre.
+    fn field(&self, z: Vec2) -> Vec2 {
+        let inv = conj(z)/z.length_squared();
+        let mut power = inv;
+        let mut sum = Vec2::ZERO;
+
+        for a in &self.0 {
+            sum += mul(*a, power);
+            power = mul(power, inv);
+        }
+
+        conj(sum)
+    }
+
+    // The potential of the charges, -sum_k q_k ln|z-z_k|.
+    fn potential(&self, z: Vec2) -> f32 {
+        let inv = conj(z)/z.length_squared();
+        let mut power = inv;
+        let mut sum = -self.0[0].x*z.length().ln();
+
+        for (p, a) in self.0.iter().enumerate().skip(1) {
+            sum += mul(*a, power).x/p as f32;
+            power = mul(power, inv);
+        }
+
+        sum
+    }
+}
+
+struct Node {
+    center: Vec2,
+    // Distance from the centre to the furthest charge or current in the node
+    radius: f32,
+    charges: Moments,
+    currents: Moments,
+    // Range of the tree's body order held in this node
+    start: usize,
+    end: usize,
+    children: Vec<usize>
+}
+
+
+
+// The fields of a set of bodies, approximated with a Barnes-Hut quadtree. Bodies are
+// grouped by position into nested square cells, and a cell which looks small enough from
+// where the field is wanted, its size over its distance being under the opening angle, is
+// replaced by a multipole expansion of the charge and current in it. Close cells are opened,
+// and the bodies in the smallest cells are summed exactly. This takes the cost of a field
+// evaluation from O(N) to roughly O(log N).
+//
+// The expansions come from each body's charge and current samples, so are only as good as
+// the samples far away. Bodies with neither, such as global fields and grid solutions, are
+// always summed exactly. The opening angle must be below 1, and smaller angles are more
+// accurate but slower.
+//
+// The tree is a snapshot of where the bodies were when it was built.
+pub struct Quadtree<'a> {
+    bodies: &'a [Box<dyn Body>],
+    opening_angle: f32,
+    nodes: Vec<Node>,
+    // Bodies in tree order, so that every node holds a contiguous range
+    order: Vec<usize>,
+    // Position of each body in order
+    ranks: Vec<usize>,
+    direct: Vec<usize>
+}
+
+impl<'a> Quadtree<'a> {
+    pub fn new(bodies: &'a [Box<dyn Body>], opening_angle: f32) -> Self {
+        let charges: Vec<_> = bodies.iter().map(|b| b.charge_samples()).collect();
+        let currents: Vec<_> = bodies.iter().map(|b| b.current_samples()).collect();
+        let (mut order, direct): (Vec<usize>, Vec<usize>) =
+            (0..bodies.len()).partition(|&i| !(charges[i].is_empty() && currents[i].is_empty()));
+        let positions: Vec<Vec2> = bodies.iter().map(|b| b.pos()).collect();
+        let (lo, hi) = order.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(lo, hi), &i| (lo.min(positions[i]), hi.max(positions[i]))
+        );
+        let mut tree = Self {
+            bodies: bodies,
+            opening_angle: opening_angle,
+            nodes: Vec::new(),
+            order: Vec::new(),
+            ranks: vec![usize::MAX; bodies.len()],
+            direct: direct
+        };
+
+        if !order.is_empty() {
+            let builder = Builder { positions: &positions, charges: &charges, currents: &currents };
+            let (center, half) = ((lo+hi)/2., (hi-lo).max_element()/2.);
+
+            builder.build(&mut tree.nodes, &mut order, 0, center, half, 0);
+        }
+
+        for (rank, &i) in order.iter().enumerate() { tree.ranks[i] = rank; }
+
+        tree.order = order;
+        tree
+    }
+
+    // A view of the tree without one body, for the force on that body.
+    pub fn without(&self, body: usize) -> Without<'_, 'a> {
+        Without { tree: self, skip: body }
+    }
+
+    // Sum some quantity at pos over everything but the body skip, using far for accepted
+    // nodes and near for the bodies in opened leaves. When a body is skipped the sum is
+    // what it feels, so bodies whose fields don't reach pos are left out too.
+    fn walk<T: Default + Add<Output = T>>(
+        &self,
+        pos: Vec2,
+        skip: Option<usize>,
+        far: &impl Fn(&Node, Vec2) -> T,
+        near: &impl Fn(&dyn Body) -> T
+    ) -> T {
+        let near = &|b: &dyn Body| {
+            if skip.is_some() && !b.covers(pos) { T::default() } else { near(b) }
+        };
+        let direct = self.direct
+                         .iter()
+                         .filter(|&&i| Some(i) != skip)
+                         .fold(T::default(), |acc, &i| acc+near(&*self.bodies[i]));
+
+        if self.nodes.is_empty() { return direct; }
+
+        direct+self.visit(0, pos, skip.map(|s| self.ranks[s]), far, near)
+    }
+
+    fn visit<T: Default + Add<Output = T>>(
+        &self,
+        index: usize,
+        pos: Vec2,
+        skip: Option<usize>,
+        far: &impl Fn(&Node, Vec2) -> T,
+        near: &impl Fn(&dyn Body) -> T
+    ) -> T {
+        let node = &self.nodes[index];
+        let z = pos-node.center;
+        let holds_skip = skip.is_some_and(|s| (node.start..node.end).contains(&s));
+
+        if !holds_skip && node.radius < self.opening_angle*z.length() { far(node, z) }
+        else if node.children.is_empty() {
+            (node.start..node.end).filter(|&rank| Some(rank) != skip)
+                                  .fold(T::default(), |acc, rank| {
+                                      acc+near(&*self.bodies[self.order[rank]])
+                                  })
+        }
+        else {
+            node.children.iter().fold(T::default(), |acc, &child| {
+                acc+self.visit(child, pos, skip, far, near)
+            })
+        }
+    }
+
+    fn e_field_without(&self, pos: Vec2, skip: Option<usize>) -> Vec2 {
+        self.walk(pos, skip, &|n, z| n.charges.field(z), &|b| b.e_field(pos))
+    }
+
+    fn potential_without(&self, pos: Vec2, skip: Option<usize>) -> f32 {
+        self.walk(pos, skip, &|n, z| n.charges.potential(z), &|b| b.potential(pos))
+    }
+
+    // Currents give the field of charges turned a quarter anticlockwise.
+    fn b_field_without(&self, pos: Vec2, skip: Option<usize>) -> Vec3 {
+        self.walk(
+            pos, skip, &|n, z| n.currents.field(z).perp().extend(0.), &|b| b.b_field(pos)
+        )
+    }
+
+    fn vector_potential_without(&self, pos: Vec2, skip: Option<usize>) -> f32 {
+        self.walk(pos, skip, &|n, z| n.currents.potential(z), &|b| b.vector_potential(pos))
+    }
+}
+
+struct Builder<'b> {
+    positions: &'b [Vec2],
+    charges: &'b [Vec<(Vec2, f32)>],
+    currents: &'b [Vec<(Vec2, f32)>]
+}
+
+impl<'b> Builder<'b> {
+    // Add the node for a square cell holding the given bodies, which are reordered so that
+    // each child's bodies are together. Returns the node's index.
+    fn build(
+        &self,
+        nodes: &mut Vec<Node>,
+        bodies: &mut [usize],
+        start: usize,
+        center: Vec2,
+        half: f32,
+        depth: usize
+    ) -> usize {
+        let charges = bodies.iter().flat_map(|&i| self.charges[i].iter().copied());
+        let currents = bodies.iter().flat_map(|&i| self.currents[i].iter().copied());
+        let radius = charges.clone()
+                            .chain(currents.clone())
+                            .fold(0., |r: f32, (p, _)| r.max(p.distance(center)));
+        let index = nodes.len();
+
+        nodes.push(Node {
+            center: center,
+            radius: radius,
+            charges: Moments::new(charges, center),
+            currents: Moments::new(currents, center),
+            start: start,
+            end: start+bodies.len(),
+            children: Vec::new()
+        });
+
+        if bodies.len() <= LEAF_SIZE || depth == MAX_DEPTH { return index; }
+
+        let quadrant = |i: &usize| {
+            let p = self.positions[*i];
+
+            (p.x >= center.x) as usize+2*(p.y >= center.y) as usize
+        };
+
+        bodies.sort_by_key(quadrant);
+
+        let mut children = Vec::new();
+        let mut offset = 0;
+
+        for q in 0..4 {
+            let count = bodies[offset..].iter().take_while(|i| quadrant(i) == q).count();
+
+            if count > 0 {
+                let sign = Vec2::new(
+                    if q%2 == 1 { 1. } else { -1. }, if q >= 2 { 1. } else { -1. }
+                );
+                let child = self.build(
+                    nodes, &mut bodies[offset..offset+count], start+offset,
+                    center+sign*half/2., half/2., depth+1
+                );
+
+                children.push(child);
+            }
+
+            offset += count;
+        }
+
+        nodes[index].children = children;
+        index
+    }
+}
+
+impl<'a> Field for Quadtree<'a> {
+    fn e_field(&self, pos: Vec2) -> Vec2 { self.e_field_without(pos, None) }
+    fn potential(&self, pos: Vec2) -> f32 { self.potential_without(pos, None) }
+    fn b_field(&self, pos: Vec2) -> Vec3 { self.b_field_without(pos, None) }
+    fn vector_potential(&self, pos: Vec2) -> f32 { self.vector_potential_without(pos, None) }
+}
+
+// The fields of a tree's bodies apart from one.
+pub struct Without<'t, 'a> {
+    tree: &'t Quadtree<'a>,
+    skip: usize
+}
+
+impl<'t, 'a> Field for Without<'t, 'a> {
+    fn e_field(&self, pos: Vec2) -> Vec2 { self.tree.e_field_without(pos, Some(self.skip)) }
+    fn potential(&self, pos: Vec2) -> f32 { self.tree.potential_without(pos, Some(self.skip)) }
+    fn b_field(&self, pos: Vec2) -> Vec3 { self.tree.b_field_without(pos, Some(self.skip)) }
+
+    fn vector_potential(&self, pos: Vec2) -> f32 {
+        self.tree.vector_potential_without(pos, Some(self.skip))
+    }
+}
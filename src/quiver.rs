@@ -0,0 +1,126 @@
+use glam::Vec2;
+use crate::bodies::Field;
+use crate::canvas::{Canvas, Color, Viewport};
+use crate::util::hash_unit;
+
+
+
+// How the length of an arrow follows the strength of the field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ArrowScaling {
+    Linear,
+    // Logarithmic, so that weak fields still show while strong ones don't swamp them
+    #[default]
+    Log,
+    // Every arrow the same length, showing only direction
+    Normalised
+}
+
+impl ArrowScaling {
+    pub const ALL: [ArrowScaling; 3] = [Self::Linear, Self::Log, Self::Normalised];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Log => "Log",
+            Self::Normalised => "Normalised"
+        }
+    }
+
+    // Length as a fraction of the longest arrow, for a field strength relative to a typical
+    // one. Fields stronger than the typical one are clamped, so arrows near charges stay
+    // within their own cell.
+    fn length(&self, strength: f32) -> f32 {
+        // Strengths below 1/DECADES of the typical one are on the linear part of the log scale
+        const DECADES: f32 = 100.;
+
+        match self {
+            Self::Linear => strength.min(1.),
+            Self::Log => ((1.+DECADES*strength).ln()/(1.+DECADES).ln()).min(1.),
+            Self::Normalised => 1.
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuiverStyle {
+    // Pixels between neighbouring arrows
+    pub spacing: f32,
+    // Move each arrow a random but fixed distance from its grid point, which hides the
+    // grid's own pattern
+    pub jitter: bool,
+    pub scaling: ArrowScaling,
+    pub color: Color
+}
+
+impl Default for QuiverStyle {
+    fn default() -> Self {
+        Self {
+            spacing: 30.,
+            jitter: false,
+            scaling: ArrowScaling::default(),
+            color: Color::rgb(0.6, 0.8, 1.)
+        }
+    }
+}
+
+// A grid of arrows showing the direction and strength of the electric field over a
+// viewport. Each arrow is centred on its grid point.
+pub struct Quiver {
+    // Middle and full extent of each arrow, in world units
+    arrows: Vec<(Vec2, Vec2)>,
+    weight: f32,
+    color: Color
+}
+
+impl Quiver {
+    // The grid is fixed to the world rather than the viewport, so that arrows stay put as
+    // long as the field does. The typical strength the arrows are scaled by is that of the
+    // strongest 5% of them, so that a few arrows close to charges don't shrink the rest.
+    pub fn new(body: &(impl Field + ?Sized), viewport: &Viewport, style: QuiverStyle) -> Self {
+        let spacing = style.spacing.max(1.)/viewport.scale;
+        let (lo, hi) = viewport.bounds();
+        let (i0, j0) = ((lo.x/spacing).floor() as i64, (lo.y/spacing).floor() as i64);
+        let (i1, j1) = ((hi.x/spacing).ceil() as i64, (hi.y/spacing).ceil() as i64);
+        let mut samples = Vec::new();
+
+        for j in j0..=j1 {
+            for i in i0..=i1 {
+                let jitter = Vec2::new(hash_unit(i, j, 1), hash_unit(i, j, 2))-Vec2::splat(0.5);
+                let offset = if style.jitter { 0.8*jitter } else { Vec2::ZERO };
+                let pos = spacing*(Vec2::new(i as f32, j as f32)+offset);
+                let e = body.e_field(pos);
+
+                if e.is_finite() && e != Vec2::ZERO { samples.push((pos, e)); }
+            }
+        }
+
+        let mut strengths: Vec<f32> = samples.iter().map(|(_, e)| e.length()).collect();
+        let typical = if strengths.is_empty() { 1. } else {
+            let upper = (strengths.len()-1)*95/100;
+
+            *strengths.select_nth_unstable_by(upper, f32::total_cmp).1
+        };
+        let longest = 0.8*spacing;
+        let arrows = samples.into_iter()
+                            .map(|(pos, e)| {
+                                let length = longest*style.scaling.length(e.length()/typical);
+
+                                (pos, e.normalize()*length)
+                            })
+                            .collect();
+
+        Self { arrows: arrows, weight: 1./viewport.scale, color: style.color }
+    }
+
+    pub fn draw(&self, canvas: &mut dyn Canvas) {
+        for (pos, v) in &self.arrows {
+            let tip = *pos+*v/2.;
+            let back = tip-0.3**v;
+            let side = 0.15*v.perp();
+
+            canvas.line(*pos-*v/2., back, self.weight, self.color);
+            canvas.tri([tip, back+side, back-side], self.color);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use glam::Vec2;
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Stroke, Transform};
+use crate::bodies::{Body, Field};
+use crate::canvas::*;
+use crate::heatmap::{self, HeatmapScale, HeatmapStyle};
+use crate::images::ImageBoundary;
+use crate::lic::Lic;
+use crate::quiver::Quiver;
+
+
+
+// Canvas which rasterises onto a pixmap on the CPU. Coordinates and stroke weights are
+// given in world units and converted to pixels through the viewport.
+pub struct RasterCanvas {
+    viewport: Viewport,
+    pixmap: Pixmap
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+
+    paint.set_color_rgba8(
+        (color.r*255.).round() as u8, (color.g*255.).round() as u8,
+        (color.b*255.).round() as u8, (color.a*255.).round() as u8
+    );
+    paint.anti_alias = true;
+    paint
+}
+
+impl RasterCanvas {
+    // Returns None if the viewport is empty or too large to allocate.
+    pub fn new(viewport: Viewport) -> Option<Self> {
+        Some(Self { viewport: viewport, pixmap: Pixmap::new(viewport.width, viewport.height)? })
+    }
+
+    pub fn background(&mut self, color: Color) {
+        let c = tiny_skia::Color::from_rgba(color.r, color.g, color.b, color.a);
+
+        self.pixmap.fill(c.unwrap_or(tiny_skia::Color::BLACK));
+    }
+
+    // Cover the whole canvas with a heat map of the body's potential.
+    pub fn heatmap(&mut self, body: &(impl Field + ?Sized), style: HeatmapStyle) {
+        let values = heatmap::sample_potential(body, &self.viewport);
+        let scale = HeatmapScale::fit(&values, style);
+        let colors: Vec<Color> = values.into_iter().map(|v| scale.color(v)).collect();
+
+        self.image(&colors);
+    }
+
+    // Cover the whole canvas with an image the same size as it, such as a LIC texture.
+    pub fn image(&mut self, pixels: &[Color]) {
+        for (pixel, c) in self.pixmap.pixels_mut().iter_mut().zip(pixels) {
+            let a = c.a;
+
+            *pixel = PremultipliedColorU8::from_rgba(
+                (c.r*a*255.).round() as u8, (c.g*a*255.).round() as u8,
+                (c.b*a*255.).round() as u8, (a*255.).round() as u8
+            ).unwrap();
+        }
+    }
+
+    pub fn finish(self) -> Pixmap {
+        self.pixmap
+    }
+
+    fn stroke(&mut self, builder: PathBuilder, weight: f32, color: Color) {
+        let stroke = Stroke { width: weight*self.viewport.scale, ..Stroke::default() };
+
+        if let Some(path) = builder.finish() {
+            self.pixmap.stroke_path(&path, &paint(color), &stroke, Transform::identity(), None);
+        }
+    }
+
+    fn path(&self, points: &[Vec2]) -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        let mut points = points.iter().filter(|p| p.is_finite());
+
+        if let Some(p) = points.next() {
+            let p = self.viewport.to_pixel(*p);
+
+            builder.move_to(p.x, p.y);
+        }
+
+        for p in points {
+            let p = self.viewport.to_pixel(*p);
+
+            builder.line_to(p.x, p.y);
+        }
+
+        builder
+    }
+}
+
+impl Canvas for RasterCanvas {
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: Color) {
+        let c = self.viewport.to_pixel(center);
+
+        if let Some(path) = PathBuilder::from_circle(c.x, c.y, radius*self.viewport.scale) {
+            self.pixmap.fill_path(
+                &path, &paint(color), FillRule::Winding, Transform::identity(), None
+            );
+        }
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: Color) {
+        let builder = self.path(&[start, end]);
+
+        self.stroke(builder, weight, color);
+    }
+
+    fn tri(&mut self, points: [Vec2; 3], color: Color) {
+        let mut builder = self.path(&points);
+
+        builder.close();
+
+        if let Some(path) = builder.finish() {
+            self.pixmap.fill_path(
+                &path, &paint(color), FillRule::Winding, Transform::identity(), None
+            );
+        }
+    }
+
+    fn polyline(&mut self, points: &[Vec2], weight: f32, color: Color) {
+        let builder = self.path(points);
+
+        self.stroke(builder, weight, color);
+    }
+}
+
+
+
+// What a raster image is drawn on.
+pub enum Background<'a> {
+    Black,
+    // A heat map of the potential of a field in the given style
+    Heatmap(&'a dyn Field, HeatmapStyle),
+    // A LIC texture, which must be the size of the image
+    Lic(&'a Lic)
+}
+
+// Render the scene as the GUI would show it on the given background, optionally with arrows
+// showing the field underneath the lines. Returns None if the viewport is empty or too large.
+#[allow(clippy::too_many_arguments)]
+pub fn render_raster(
+    viewport: Viewport,
+    bodies: &[Box<dyn Body>],
+    boundary: Option<&ImageBoundary>,
+    isopotentials: &[Vec<Vec2>],
+    field_lines: &[Vec<Vec2>],
+    magnetic_lines: &[Vec<Vec2>],
+    background: Background,
+    quiver: Option<&Quiver>
+) -> Option<Pixmap> {
+    let mut canvas = RasterCanvas::new(viewport)?;
+
+    match background {
+        Background::Black => canvas.background(BLACK),
+        Background::Heatmap(field, style) => canvas.heatmap(field, style),
+        Background::Lic(lic) => canvas.image(lic.pixels())
+    }
+
+    if let Some(q) = quiver { q.draw(&mut canvas); }
+
+    draw_scene(&mut canvas, bodies, boundary, isopotentials, field_lines, magnetic_lines);
+
+    Some(canvas.finish())
+}
@@ -0,0 +1,498 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::bodies::*;
+use crate::collision::Collisions;
+use crate::conductor::{Condition, Conductor, Electrode, Outline};
+use crate::dielectric::{Dielectric, Region};
+use crate::images::ImageBoundary;
+use crate::poisson::{self, Boundaries, Grid};
+use crate::simulation::{self, Integrator, DEFAULT_TIMESTEP};
+
+
+
+// The scene format version written by this build. Files with a newer version are rejected
+// rather than half-loaded, while older ones load with defaults for anything added since.
+//
+// 1: bodies and traced lines
+// 2: simulation settings, magnetic field lines, image boundaries, the Barnes-Hut opening
+//    angle, and current wires, sheets, conductors, dielectrics and Poisson grids
+pub const SCENE_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+    InvalidSetting(String),
+    InvalidBody { index: usize, reason: String }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not access scene file: {}", e),
+            Self::Parse(e) => write!(f, "malformed scene file at {}", e),
+            Self::Serialize(e) => write!(f, "could not write scene: {}", e),
+            Self::UnsupportedVersion(v) => write!(
+                f, "scene version {} is not supported (newest supported is {})",
+                v, SCENE_VERSION
+            ),
+            Self::InvalidSetting(reason) => write!(f, "{}", reason),
+            Self::InvalidBody { index, reason } => write!(f, "body {}: {}", index, reason)
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> Self { Self::Io(e) }
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+    fn from(e: ron::error::SpannedError) -> Self { Self::Parse(e) }
+}
+
+impl From<ron::Error> for SceneError {
+    fn from(e: ron::Error) -> Self { Self::Serialize(e) }
+}
+
+
+
+fn default_mass() -> f32 { 1. }
+fn default_timestep() -> f32 { DEFAULT_TIMESTEP }
+fn default_panels() -> usize { 64 }
+
+const MAX_PANELS: usize = 500;
+
+// On-disk description of a body. This is kept separate from the body structs themselves so
+// that their internals can change without breaking saved scenes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum SceneBody {
+    PointCharge {
+        charge: f32,
+        #[serde(default = "default_mass")] mass: f32,
+        pos: Vec2,
+        #[serde(default)] vel: Vec2
+    },
+    Dipole {
+        dipole: f32,
+        #[serde(default = "default_mass")] mass: f32,
+        #[serde(default)] angle: f32,
+        pos: Vec2,
+        #[serde(default)] vel: Vec2,
+        #[serde(default)] ang_vel: f32
+    },
+    CircleCharge {
+        charge: f32,
+        #[serde(default = "default_mass")] mass: f32,
+        radius: f32,
+        pos: Vec2,
+        #[serde(default)] vel: Vec2
+    },
+    GlobalField {
+        field: Vec2
+    },
+    // Out of the plane
+    GlobalMagneticField {
+        field: f32
+    },
+    // Line charges are fixed in place unless given a mass.
+    LineCharge {
+        start: Vec2,
+        end: Vec2,
+        charge: f32,
+        #[serde(default)] mass: Option<f32>,
+        #[serde(default)] vel: Vec2,
+        #[serde(default)] ang_vel: f32
+    },
+    CurrentWire {
+        current: f32,
+        #[serde(default = "default_mass")] mass: f32,
+        pos: Vec2,
+        #[serde(default)] vel: Vec2
+    },
+    // Fixed in place unless given a mass, like line charges.
+    CurrentSheet {
+        start: Vec2,
+        end: Vec2,
+        current: f32,
+        #[serde(default)] mass: Option<f32>,
+        #[serde(default)] vel: Vec2,
+        #[serde(default)] ang_vel: f32
+    },
+    // Panels are per electrode
+    Conductor {
+        electrodes: Vec<Electrode>,
+        #[serde(default = "default_panels")] panels: usize
+    },
+    // Panels are per region
+    Dielectric {
+        regions: Vec<Region>,
+        #[serde(default = "default_panels")] panels: usize
+    },
+    // The potential of the sources solved on a grid, which stands in for them
+    PoissonGrid {
+        grid: Grid,
+        #[serde(default)] boundaries: Boundaries,
+        sources: Vec<SceneBody>
+    },
+    Group(Vec<SceneBody>)
+}
+
+fn check(ok: bool, reason: &str) -> Result<(), String> {
+    if ok { Ok(()) } else { Err(reason.to_string()) }
+}
+
+fn check_finite(values: &[f32]) -> Result<(), String> {
+    check(values.iter().all(|v| v.is_finite()), "all values must be finite")
+}
+
+// Bodies which are fixed in place unless given a mass.
+fn check_optional_mass(mass: Option<f32>) -> Result<(), String> {
+    match mass {
+        Some(mass) => {
+            check_finite(&[mass])?;
+            check(mass > 0., "mass must be positive")
+        }
+        None => Ok(())
+    }
+}
+
+fn check_outline(outline: &Outline) -> Result<(), String> {
+    match outline {
+        Outline::Polygon(corners) => {
+            let coords: Vec<f32> = corners.iter().flat_map(|c| [c.x, c.y]).collect();
+            let next = corners.iter().cycle().skip(1);
+
+            check(corners.len() >= 3, "polygon needs at least three corners")?;
+            check_finite(&coords)?;
+            check(
+                corners.iter().zip(next).all(|(a, b)| a != b),
+                "neighbouring polygon corners must differ"
+            )
+        }
+        Outline::Circle { center, radius } => {
+            check_finite(&[center.x, center.y, *radius])?;
+            check(*radius > 0., "radius must be positive")
+        }
+    }
+}
+
+fn check_electrode(electrode: &Electrode) -> Result<(), String> {
+    let (Condition::Potential(v) | Condition::Floating(v)) = electrode.condition;
+
+    check_outline(&electrode.outline)?;
+    check_finite(&[v])
+}
+
+fn check_region(region: &Region) -> Result<(), String> {
+    check_outline(&region.outline)?;
+    check_finite(&[region.permittivity])?;
+    check(region.permittivity >= 1., "permittivity must be at least 1")
+}
+
+fn check_boundary(boundary: &ImageBoundary) -> Result<(), String> {
+    match *boundary {
+        ImageBoundary::Line { point, normal } => {
+            check_finite(&[point.x, point.y, normal.x, normal.y])?;
+            check(normal != Vec2::ZERO, "boundary normal must be non-zero")
+        }
+        ImageBoundary::Circle { center, radius, condition } => {
+            let (Condition::Potential(v) | Condition::Floating(v)) = condition;
+
+            check_finite(&[center.x, center.y, radius, v])?;
+            check(radius > 0., "radius must be positive")?;
+
+            // Charge at the centre gives no potential on a circle of radius 1, so such a
+            // circle can only be held at a potential if it is slightly larger or smaller
+            match condition {
+                Condition::Potential(_) => {
+                    check(radius.ln().abs() > 1e-3, "circle at a potential can't have radius 1")
+                }
+                Condition::Floating(_) => Ok(())
+            }
+        }
+    }
+}
+
+// Only bodies whose charge stays where it is put can be imaged in a boundary. Conductors and
+// dielectrics would need images of their induced charge, grid solutions of their whole
+// grid, and currents aren't charges at all.
+fn check_imaged(body: &SceneBody) -> Result<(), String> {
+    match body {
+        SceneBody::PointCharge { .. } | SceneBody::Dipole { .. }
+        | SceneBody::CircleCharge { .. } | SceneBody::LineCharge { .. } => Ok(()),
+        SceneBody::Group(bodies) => bodies.iter().try_for_each(check_imaged),
+        _ => Err(
+            "only point, dipole, circle and line charges can be imaged in a boundary".to_string()
+        )
+    }
+}
+
+impl SceneBody {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::PointCharge { charge, mass, pos, vel } => {
+                check_finite(&[*charge, *mass, pos.x, pos.y, vel.x, vel.y])?;
+                check(*mass > 0., "mass must be positive")
+            }
+            Self::Dipole { dipole, mass, angle, pos, vel, ang_vel } => {
+                check_finite(&[*dipole, *mass, *angle, pos.x, pos.y, vel.x, vel.y, *ang_vel])?;
+                check(*mass > 0., "mass must be positive")
+            }
+            Self::CircleCharge { charge, mass, radius, pos, vel } => {
+                check_finite(&[*charge, *mass, *radius, pos.x, pos.y, vel.x, vel.y])?;
+                check(*mass > 0., "mass must be positive")?;
+                check(*radius > 0., "radius must be positive")
+            }
+            Self::GlobalField { field } => check_finite(&[field.x, field.y]),
+            Self::GlobalMagneticField { field } => check_finite(&[*field]),
+            Self::LineCharge { start, end, charge, mass, vel, ang_vel } => {
+                check_finite(&[start.x, start.y, end.x, end.y, *charge, vel.x, vel.y, *ang_vel])?;
+                check(start != end, "line charge must have non-zero length")?;
+                check_optional_mass(*mass)
+            }
+            Self::CurrentWire { current, mass, pos, vel } => {
+                check_finite(&[*current, *mass, pos.x, pos.y, vel.x, vel.y])?;
+                check(*mass > 0., "mass must be positive")
+            }
+            Self::CurrentSheet { start, end, current, mass, vel, ang_vel } => {
+                check_finite(&[start.x, start.y, end.x, end.y, *current, vel.x, vel.y, *ang_vel])?;
+                check(start != end, "current sheet must have non-zero length")?;
+                check_optional_mass(*mass)
+            }
+            Self::Conductor { electrodes, panels } => {
+                check(!electrodes.is_empty(), "conductor needs at least one electrode")?;
+                check((3..=MAX_PANELS).contains(panels), "panels must be between 3 and 500")?;
+                electrodes.iter().try_for_each(check_electrode)
+            }
+            Self::Dielectric { regions, panels } => {
+                check(!regions.is_empty(), "dielectric needs at least one region")?;
+                check((3..=MAX_PANELS).contains(panels), "panels must be between 3 and 500")?;
+                regions.iter().try_for_each(check_region)
+            }
+            Self::PoissonGrid { grid, boundaries, sources } => {
+                poisson::check_grid(grid, boundaries)?;
+                sources.iter().try_for_each(|b| b.validate())
+            }
+            Self::Group(bodies) => bodies.iter().try_for_each(|b| b.validate())
+        }
+    }
+
+    // Build the body from parameters which have been validated. Only solving a grid can still
+    // fail.
+    fn into_body_unchecked(self) -> Result<Box<dyn Body>, String> {
+        Ok(match self {
+            Self::PointCharge { charge, mass, pos, vel } => {
+                Box::new(PointCharge { charge, mass, pos, vel })
+            }
+            Self::Dipole { dipole, mass, angle, pos, vel, ang_vel } => {
+                Box::new(Dipole::new(dipole, mass, angle, pos, vel).with_ang_vel(ang_vel))
+            }
+            Self::CircleCharge { charge, mass, radius, pos, vel } => {
+                Box::new(CircleCharge { charge, mass, radius, pos, vel })
+            }
+            Self::GlobalField { field } => Box::new(GlobalField { field }),
+            Self::GlobalMagneticField { field } => Box::new(GlobalMagneticField { field }),
+            Self::LineCharge { start, end, charge, mass, vel, ang_vel } => {
+                let line = LineCharge::new(start, end, charge);
+
+                match mass {
+                    Some(mass) => Box::new(line.with_motion(mass, vel, ang_vel)),
+                    None => Box::new(line)
+                }
+            }
+            Self::CurrentWire { current, mass, pos, vel } => {
+                Box::new(CurrentWire { current, mass, pos, vel })
+            }
+            Self::CurrentSheet { start, end, current, mass, vel, ang_vel } => {
+                let sheet = CurrentSheet::new(start, end, current);
+
+                match mass {
+                    Some(mass) => Box::new(sheet.with_motion(mass, vel, ang_vel)),
+                    None => Box::new(sheet)
+                }
+            }
+            Self::Conductor { electrodes, panels } => {
+                Box::new(Conductor::new(electrodes, panels))
+            }
+            Self::Dielectric { regions, panels } => {
+                Box::new(Dielectric::new(regions, panels))
+            }
+            Self::PoissonGrid { grid, boundaries, sources } => {
+                let mut sources = sources.into_iter()
+                                         .map(|b| b.into_body_unchecked())
+                                         .collect::<Result<Vec<_>, _>>()?;
+
+                simulation::induce_charges(&mut sources);
+
+                Box::new(poisson::solve(grid, boundaries, sources)?)
+            }
+            Self::Group(bodies) => {
+                let group = bodies.into_iter()
+                                  .map(|b| b.into_body_unchecked())
+                                  .collect::<Result<Vec<_>, _>>()?;
+
+                Box::new(group)
+            }
+        })
+    }
+
+    // Build the body described, checking that its parameters make physical sense.
+    pub fn into_body(self) -> Result<Box<dyn Body>, String> {
+        self.validate()?;
+        self.into_body_unchecked()
+    }
+}
+
+
+
+#[derive(Deserialize)]
+#[serde(rename = "Scene")]
+struct SceneHeader {
+    version: u32
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    pub version: u32,
+    pub bodies: Vec<SceneBody>,
+    #[serde(default)]
+    pub isopotentials: Vec<Vec<Vec2>>,
+    #[serde(default)]
+    pub field_lines: Vec<Vec<Vec2>>,
+    // Magnetic field lines, traced as contours of the vector potential
+    #[serde(default)]
+    pub magnetic_lines: Vec<Vec<Vec2>>,
+    #[serde(default)]
+    pub integrator: Integrator,
+    #[serde(default = "default_timestep")]
+    pub timestep: f32,
+    #[serde(default)]
+    pub collisions: Option<Collisions>,
+    // A conducting line or circle which the bodies are imaged in
+    #[serde(default)]
+    pub boundary: Option<ImageBoundary>,
+    // Opening angle of the Barnes-Hut tree used to sum the fields of the bodies, or None to
+    // sum them exactly
+    #[serde(default)]
+    pub opening_angle: Option<f32>
+}
+
+impl Scene {
+    pub fn new(
+        bodies: &[Box<dyn Body>],
+        isopotentials: &[Vec<Vec2>],
+        field_lines: &[Vec<Vec2>]
+    ) -> Self {
+        Self {
+            version: SCENE_VERSION,
+            bodies: bodies.iter().map(|b| b.to_scene()).collect(),
+            isopotentials: isopotentials.to_vec(),
+            field_lines: field_lines.to_vec(),
+            magnetic_lines: Vec::new(),
+            integrator: Integrator::default(),
+            timestep: DEFAULT_TIMESTEP,
+            collisions: None,
+            boundary: None,
+            opening_angle: None
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SceneError> {
+        // Check the version before anything else, so that a file from a newer build gives
+        // a version error rather than a confusing complaint about some unknown field.
+        let header: SceneHeader = ron::de::from_str(text)?;
+
+        if header.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(header.version));
+        }
+
+        let scene: Scene = ron::de::from_str(text)?;
+
+        if !(scene.timestep > 0. && scene.timestep.is_finite()) {
+            return Err(SceneError::InvalidSetting("timestep must be positive".to_string()));
+        }
+
+        if let Some(c) = scene.collisions {
+            if !(0. ..=1.).contains(&c.restitution) {
+                return Err(SceneError::InvalidSetting(
+                    "restitution must be between 0 and 1".to_string()
+                ));
+            }
+        }
+
+        if let Some(theta) = scene.opening_angle {
+            if !(theta > 0. && theta < 1.) {
+                return Err(SceneError::InvalidSetting(
+                    "opening angle must be between 0 and 1".to_string()
+                ));
+            }
+        }
+
+        if let Some(b) = &scene.boundary {
+            check_boundary(b).map_err(SceneError::InvalidSetting)?;
+        }
+
+        for (i, body) in scene.bodies.iter().enumerate() {
+            let imaged = if scene.boundary.is_some() { check_imaged(body) } else { Ok(()) };
+
+            body.validate()
+                .and(imaged)
+                .map_err(|reason| SceneError::InvalidBody { index: i, reason })?;
+        }
+
+        Ok(scene)
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        let config = ron::ser::PrettyConfig::new().depth_limit(2);
+
+        Ok(ron::ser::to_string_pretty(self, config)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        Ok(fs::write(path, self.to_ron()?)?)
+    }
+
+    // Whether the other scene has the same field, with the same bodies, boundary and opening
+    // angle, whatever lines have been traced in it.
+    pub fn same_field(&self, other: &Scene) -> bool {
+        self.bodies == other.bodies && self.boundary == other.boundary
+        && self.opening_angle == other.opening_angle
+    }
+
+    // Build the scene's bodies, with the charge induced on any conductors already settled.
+    // The scene has already been validated when parsed, but one built in code may not have
+    // been, so this checks again.
+    pub fn bodies(&self) -> Result<Vec<Box<dyn Body>>, SceneError> {
+        let mut bodies = self.bodies.iter()
+                                    .cloned()
+                                    .enumerate()
+                                    .map(|(i, b)| {
+                                        let imaged = if self.boundary.is_some() {
+                                            check_imaged(&b)
+                                        } else { Ok(()) };
+
+                                        imaged.and_then(|_| b.into_body()).map_err(|reason| {
+                                            SceneError::InvalidBody { index: i, reason }
+                                        })
+                                    })
+                                    .collect::<Result<Vec<_>, _>>()?;
+
+        simulation::induce_charges(&mut bodies);
+
+        Ok(bodies)
+    }
+}
@@ -0,0 +1,536 @@
+use glam::{Mat2, Vec2};
+use serde::{Deserialize, Serialize};
+use crate::bodies::{Body, Field, Kinematics, Others, Sum};
+use crate::collision::{self, Collisions};
+use crate::images::ImageBoundary;
+use crate::quadtree::Quadtree;
+
+
+
+// The state of every body packed into one vector, six values per body. The first three are
+// generalised positions (x, y, angle) and the last three the matching velocities, so the
+// velocity of value i is at i+3.
+const STRIDE: usize = 6;
+
+fn gather(bodies: &[Box<dyn Body>]) -> Vec<f32> {
+    let mut y = Vec::with_capacity(bodies.len()*STRIDE);
+
+    for b in bodies {
+        let k = b.kinematics();
+
+        y.extend([k.pos.x, k.pos.y, k.angle, k.vel.x, k.vel.y, k.ang_vel]);
+    }
+
+    y
+}
+
+fn scatter(bodies: &mut [Box<dyn Body>], y: &[f32]) {
+    for (b, s) in bodies.iter_mut().zip(y.chunks_exact(STRIDE)) {
+        b.set_kinematics(Kinematics {
+            pos: Vec2::new(s[0], s[1]),
+            vel: Vec2::new(s[3], s[4]),
+            angle: s[2],
+            ang_vel: s[5]
+        });
+    }
+}
+
+// Acceleration divided by inertia, taking infinite inertia to mean that the body is fixed.
+fn accel(force: f32, inertia: f32) -> f32 {
+    if inertia.is_finite() { force/inertia } else { 0. }
+}
+
+// Settings which decide the forces between bodies.
+#[derive(Clone, Copy)]
+struct Forces<'a> {
+    boundary: Option<&'a ImageBoundary>,
+    opening_angle: Option<f32>
+}
+
+// Everything the bodies feel besides each other directly, built afresh from their current
+// positions for each force evaluation.
+struct Surroundings<'a> {
+    images: Vec<Box<dyn Body>>,
+    tree: Option<Quadtree<'a>>
+}
+
+impl<'a> Surroundings<'a> {
+    fn new(bodies: &'a [Box<dyn Body>], forces: Forces) -> Self {
+        Self {
+            images: forces.boundary.map(|b| b.images(bodies)).unwrap_or_default(),
+            tree: forces.opening_angle.map(|theta| Quadtree::new(bodies, theta))
+        }
+    }
+
+    // Call f with the field felt by body i, from every other body and from the images.
+    fn felt_by<R>(&self, bodies: &[Box<dyn Body>], i: usize, f: impl FnOnce(&dyn Field) -> R) -> R {
+        match &self.tree {
+            Some(tree) => f(&Sum(&tree.without(i), &self.images)),
+            None => {
+                let (before, rest) = bodies.split_at(i);
+
+                f(&Sum(&Others { before: before, after: &rest[1..] }, &self.images))
+            }
+        }
+    }
+}
+
+// Put the bodies in state y and find every body's linear and angular acceleration, three
+// values per body. All forces are computed from the same snapshot, including the images of
+// every body in the boundary, which move with them.
+fn accelerations(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32]
+) -> Vec<f32> {
+    let mut a = Vec::with_capacity(bodies.len()*3);
+
+    scatter(bodies, y);
+
+    let bodies = &*bodies;
+    let surroundings = Surroundings::new(bodies, forces);
+
+    for (i, body) in bodies.iter().enumerate() {
+        let (force, torque) = surroundings.felt_by(bodies, i, |f| body.force_torque(f));
+        let (mass, inertia) = (body.mass(), body.inertia());
+
+        a.extend([accel(force.x, mass), accel(force.y, mass), accel(torque, inertia)]);
+    }
+
+    a
+}
+
+// Like accelerations, but with the magnetic force on the motion of each body as a whole left
+// out. That force is -k v.perp() for a body whose charges feel a total of k = sum(q B), so it
+// is returned separately as w = k/mass, one value per body, for schemes which treat it
+// implicitly in the velocity.
+fn magnetic_accelerations(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32]
+) -> (Vec<f32>, Vec<f32>) {
+    let mut a = Vec::with_capacity(bodies.len()*3);
+    let mut w = Vec::with_capacity(bodies.len());
+
+    scatter(bodies, y);
+
+    let bodies = &*bodies;
+    let surroundings = Surroundings::new(bodies, forces);
+
+    for ((i, body), s) in bodies.iter().enumerate().zip(y.chunks_exact(STRIDE)) {
+        let (force, torque, k) = surroundings.felt_by(bodies, i, |f| {
+            let (force, torque) = body.force_torque(f);
+            let k: f32 = body.charge_samples().iter().map(|(p, q)| q*f.b_field(*p).z).sum();
+
+            (force, torque, k)
+        });
+        let force = force+k*Vec2::new(s[3], s[4]).perp();
+        let (mass, inertia) = (body.mass(), body.inertia());
+
+        a.extend([accel(force.x, mass), accel(force.y, mass), accel(torque, inertia)]);
+        w.push(accel(k, mass));
+    }
+
+    (a, w)
+}
+
+// Solve v+c*v.perp() = rhs for v. With rhs = u-c*u.perp() this turns u through the angle
+// -2*atan(c) without changing its length, which is how the magnetic force is averaged over
+// a step implicitly, so that it does no work.
+fn solve_magnetic(rhs: Vec2, c: f32) -> Vec2 {
+    (rhs-c*rhs.perp())/(1.+c*c)
+}
+
+// Time derivative of the state vector y.
+fn derivative(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32]
+) -> Vec<f32> {
+    let a = accelerations(bodies, forces, y);
+    let mut dy = Vec::with_capacity(y.len());
+
+    for (s, a) in y.chunks_exact(STRIDE).zip(a.chunks_exact(3)) {
+        dy.extend_from_slice(&s[3..]);
+        dy.extend_from_slice(a);
+    }
+
+    dy
+}
+
+// y + h*sum(c_i*k_i)
+fn step_by(y: &[f32], h: f32, terms: &[(f32, &[f32])]) -> Vec<f32> {
+    let mut out = y.to_vec();
+
+    for (c, k) in terms {
+        for (o, k) in out.iter_mut().zip(k.iter()) { *o += h*c*k; }
+    }
+
+    out
+}
+
+
+
+// Let every body with induced charge settle in the field of all the others, passing over
+// them until no induced charge changes by more than a small fraction. Bodies affect each
+// other's induced charge, so one pass is not enough. Boundary element solutions amplify
+// rounding in the field, so the tolerance cannot be much tighter.
+pub fn induce_charges(bodies: &mut [Box<dyn Body>]) {
+    const MAX_PASSES: usize = 100;
+    const TOLERANCE: f32 = 1e-3;
+
+    for _ in 0..MAX_PASSES {
+        let mut change: f32 = 0.;
+
+        for i in 0..bodies.len() {
+            let (before, rest) = bodies.split_at_mut(i);
+            let (body, after) = rest.split_first_mut().unwrap();
+
+            change = change.max(body.induce(&Others { before: before, after: after }));
+        }
+
+        if change <= TOLERANCE { break; }
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    VelocityVerlet,
+    Leapfrog,
+    Rk4,
+    // Leapfrog with the magnetic part of the Lorentz force applied as an exact rotation of
+    // the velocity, so that magnetic fields do no work.
+    Boris,
+    // Dormand-Prince 5(4) with adaptive substeps, keeping the local error in any position or
+    // velocity below the tolerance.
+    Rk45 { tolerance: f32 }
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 5] = [
+        Self::VelocityVerlet, Self::Leapfrog, Self::Rk4, Self::Boris,
+        Self::Rk45 { tolerance: 1e-3 }
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VelocityVerlet => "Velocity Verlet",
+            Self::Leapfrog => "Leapfrog",
+            Self::Rk4 => "RK4",
+            Self::Boris => "Boris",
+            Self::Rk45 { .. } => "Adaptive RK45"
+        }
+    }
+}
+
+
+
+// Advances a set of bodies through time. Each step gathers the state of every body into one
+// vector, evaluates all forces from a consistent snapshot and integrates with the chosen
+// scheme, so that Newton's third law holds between bodies.
+//
+// Physics always advances in whole steps of a fixed timestep, regardless of how much real
+// time has passed, so a given scene run for a given number of steps always ends up in the
+// same state bit for bit.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    pub integrator: Integrator,
+    pub timestep: f32,
+    // Simulated seconds per real second
+    pub speed: f32,
+    // Most fixed steps taken per call to advance. Beyond this the simulation falls behind
+    // real time rather than taking ever longer to catch up.
+    pub max_substeps: usize,
+    // Bodies pass through each other when this is None
+    pub collisions: Option<Collisions>,
+    // Conducting boundary the bodies are attracted to by their images, if any. Bodies are
+    // not stopped from crossing it.
+    pub boundary: Option<ImageBoundary>,
+    // Opening angle of the Barnes-Hut tree used for the forces, or None to sum every pair of
+    // bodies exactly
+    pub opening_angle: Option<f32>,
+    accumulator: f32,
+    steps: u64,
+    rk45_step: Option<f32>
+}
+
+pub const DEFAULT_TIMESTEP: f32 = 1./120.;
+
+impl Simulator {
+    pub fn new(integrator: Integrator) -> Self {
+        Self {
+            integrator: integrator,
+            timestep: DEFAULT_TIMESTEP,
+            speed: 1.,
+            max_substeps: 64,
+            collisions: None,
+            boundary: None,
+            opening_angle: None,
+            accumulator: 0.,
+            steps: 0,
+            rk45_step: None
+        }
+    }
+
+    pub fn with_timestep(mut self, timestep: f32) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    // Number of fixed steps taken so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // Simulated time so far.
+    pub fn time(&self) -> f64 {
+        self.steps as f64*self.timestep as f64
+    }
+
+    // Take one fixed step. Bodies may be removed if they merge on collision.
+    pub fn step(&mut self, bodies: &mut Vec<Box<dyn Body>>) {
+        induce_charges(bodies);
+        self.integrate(bodies, self.timestep);
+
+        if let Some(collisions) = &self.collisions { collision::resolve(bodies, collisions); }
+
+        self.steps += 1;
+    }
+
+    pub fn run(&mut self, bodies: &mut Vec<Box<dyn Body>>, steps: u64) {
+        for _ in 0..steps { self.step(bodies); }
+    }
+
+    // Account for elapsed real time, taking as many fixed steps as are due. Any time left
+    // over is carried to the next call. Returns the number of steps taken.
+    pub fn advance(&mut self, bodies: &mut Vec<Box<dyn Body>>, elapsed: f32) -> usize {
+        if self.timestep <= 0. { return 0; }
+
+        self.accumulator += elapsed*self.speed;
+
+        let mut taken = 0;
+
+        while self.accumulator >= self.timestep {
+            if taken == self.max_substeps {
+                self.accumulator = 0.;
+                break;
+            }
+
+            self.step(bodies);
+            self.accumulator -= self.timestep;
+            taken += 1;
+        }
+
+        taken
+    }
+
+    fn integrate(&mut self, bodies: &mut [Box<dyn Body>], dt: f32) {
+        if bodies.is_empty() || dt <= 0. { return; }
+
+        let boundary = self.boundary;
+        let forces = Forces { boundary: boundary.as_ref(), opening_angle: self.opening_angle };
+        let y = gather(bodies);
+        let y = match self.integrator {
+            Integrator::VelocityVerlet => velocity_verlet(bodies, forces, &y, dt),
+            Integrator::Leapfrog => leapfrog(bodies, forces, &y, dt),
+            Integrator::Rk4 => rk4(bodies, forces, &y, dt),
+            Integrator::Boris => boris(bodies, forces, &y, dt),
+            Integrator::Rk45 { tolerance } => {
+                let h = self.rk45_step.unwrap_or(dt).min(dt);
+                let (y, h) = rk45(bodies, forces, &y, dt, h, tolerance);
+
+                self.rk45_step = Some(h);
+                y
+            }
+        };
+
+        scatter(bodies, &y);
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self { Self::new(Integrator::default()) }
+}
+
+// The magnetic force is averaged over the step like the others, but with its end value
+// taken from the new velocity rather than the old one, which makes the velocity update a
+// small linear solve and keeps the speed of a charge in a pure magnetic field constant.
+fn velocity_verlet(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32],
+    h: f32
+) -> Vec<f32> {
+    let (a0, w0) = magnetic_accelerations(bodies, forces, y);
+    let mut y1 = y.to_vec();
+
+    for ((s, a), w) in y1.chunks_exact_mut(STRIDE).zip(a0.chunks_exact(3)).zip(&w0) {
+        let turn = -w*Vec2::new(s[3], s[4]).perp();
+        let a = [a[0]+turn.x, a[1]+turn.y, a[2]];
+
+        for i in 0..3 { s[i] += h*s[i+3]+0.5*h*h*a[i]; }
+    }
+
+    let (a1, w1) = magnetic_accelerations(bodies, forces, &y1);
+    let kicks = a0.chunks_exact(3).zip(a1.chunks_exact(3)).zip(w0.iter().zip(&w1));
+
+    for (s, ((a0, a1), (w0, w1))) in y1.chunks_exact_mut(STRIDE).zip(kicks) {
+        let vel = Vec2::new(s[3], s[4]);
+        let a = Vec2::new(a0[0]+a1[0], a0[1]+a1[1])*0.5;
+        let vel = solve_magnetic(vel-0.5*h*w0*vel.perp()+h*a, 0.5*h*w1);
+
+        s[3] = vel.x;
+        s[4] = vel.y;
+        s[5] += 0.5*h*(a0[2]+a1[2]);
+    }
+
+    y1
+}
+
+// Drift-kick-drift leapfrog, needing only one force evaluation per step. The magnetic part of
+// the kick uses the average of the old and new velocities, as in velocity_verlet.
+fn leapfrog(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32],
+    h: f32
+) -> Vec<f32> {
+    let mut y1 = y.to_vec();
+
+    for s in y1.chunks_exact_mut(STRIDE) {
+        for i in 0..3 { s[i] += 0.5*h*s[i+3]; }
+    }
+
+    let (a, w) = magnetic_accelerations(bodies, forces, &y1);
+
+    for ((s, a), w) in y1.chunks_exact_mut(STRIDE).zip(a.chunks_exact(3)).zip(&w) {
+        let vel = Vec2::new(s[3], s[4]);
+        let vel = solve_magnetic(vel-0.5*h*w*vel.perp()+h*Vec2::new(a[0], a[1]), 0.5*h*w);
+
+        s[3] = vel.x;
+        s[4] = vel.y;
+        s[5] += h*a[2];
+
+        for i in 0..3 { s[i] += 0.5*h*s[i+3]; }
+    }
+
+    y1
+}
+
+// Drift-kick-drift like leapfrog, but with the kick split in two around a rotation of the
+// velocity by the out-of-plane magnetic field. The rotation preserves speed exactly, so a
+// charge in a pure magnetic field keeps its kinetic energy however long the timestep.
+fn boris(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32],
+    h: f32
+) -> Vec<f32> {
+    let mut y1 = y.to_vec();
+
+    for s in y1.chunks_exact_mut(STRIDE) {
+        for i in 0..3 { s[i] += 0.5*h*s[i+3]; }
+    }
+
+    let (a, w) = magnetic_accelerations(bodies, forces, &y1);
+
+    for ((s, a), w) in y1.chunks_exact_mut(STRIDE).zip(a.chunks_exact(3)).zip(&w) {
+        let rest = Vec2::new(a[0], a[1]);
+        let half_kicked = Vec2::new(s[3], s[4])+0.5*h*rest;
+        let rotated = Mat2::from_angle(-2.*(0.5*w*h).atan())*half_kicked;
+        // Rounding in the rotation would otherwise let the speed wander over long runs
+        let rotated = if rotated == Vec2::ZERO { rotated }
+                      else { rotated*(half_kicked.length()/rotated.length()) };
+        let vel = rotated+0.5*h*rest;
+
+        s[3] = vel.x;
+        s[4] = vel.y;
+        s[5] += h*a[2];
+
+        for j in 0..3 { s[j] += 0.5*h*s[j+3]; }
+    }
+
+    y1
+}
+
+fn rk4(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32],
+    h: f32
+) -> Vec<f32> {
+    let k1 = derivative(bodies, forces, y);
+    let k2 = derivative(bodies, forces, &step_by(y, h, &[(0.5, &k1)]));
+    let k3 = derivative(bodies, forces, &step_by(y, h, &[(0.5, &k2)]));
+    let k4 = derivative(bodies, forces, &step_by(y, h, &[(1., &k3)]));
+
+    step_by(y, h, &[(1./6., &k1), (1./3., &k2), (1./3., &k3), (1./6., &k4)])
+}
+
+// Integrate over dt with as many Dormand-Prince substeps as the tolerance requires, starting
+// from a trial substep of h. Returns the new state and a suggested substep for next time.
+fn rk45(
+    bodies: &mut [Box<dyn Body>],
+    forces: Forces,
+    y: &[f32],
+    dt: f32,
+    mut h: f32,
+    tolerance: f32
+) -> (Vec<f32>, f32) {
+    const MIN_STEP: f32 = 1e-6;
+    let mut y = y.to_vec();
+    let mut t = 0.;
+    let mut next_h = h;
+
+    while dt-t > 1e-6*dt {
+        let proposed = h;
+
+        h = h.min(dt-t).max(MIN_STEP);
+
+        let k1 = derivative(bodies, forces, &y);
+        let k2 = derivative(bodies, forces, &step_by(&y, h, &[(1./5., &k1)]));
+        let k3 = derivative(bodies, forces, &step_by(&y, h, &[(3./40., &k1), (9./40., &k2)]));
+        let k4 = derivative(bodies, forces, &step_by(&y, h, &[
+            (44./45., &k1), (-56./15., &k2), (32./9., &k3)
+        ]));
+        let k5 = derivative(bodies, forces, &step_by(&y, h, &[
+            (19372./6561., &k1), (-25360./2187., &k2), (64448./6561., &k3), (-212./729., &k4)
+        ]));
+        let k6 = derivative(bodies, forces, &step_by(&y, h, &[
+            (9017./3168., &k1), (-355./33., &k2), (46732./5247., &k3), (49./176., &k4),
+            (-5103./18656., &k5)
+        ]));
+        let y5 = step_by(&y, h, &[
+            (35./384., &k1), (500./1113., &k3), (125./192., &k4), (-2187./6784., &k5),
+            (11./84., &k6)
+        ]);
+        let k7 = derivative(bodies, forces, &y5);
+        let err_terms = [
+            (71./57600., &k1), (-71./16695., &k3), (71./1920., &k4), (-17253./339200., &k5),
+            (22./525., &k6), (-1./40., &k7)
+        ];
+        let err = (0..y.len()).map(|i| {
+                                  err_terms.iter().map(|(c, k)| c*k[i]).sum::<f32>().abs()*h
+                              })
+                              .fold(0., f32::max);
+
+        // Standard step size control, with the growth and shrinkage clamped
+        let factor = if err > 0. { 0.9*(tolerance/err).powf(0.2) } else { 5. };
+
+        if err <= tolerance || h <= MIN_STEP {
+            t += h;
+            y = y5;
+
+            // A step cut short to land on the end of dt says little about how long the next
+            // one can be, so only full length steps decide the suggestion
+            if h == proposed { next_h = h*factor.clamp(0.2, 5.); }
+        }
+
+        h *= factor.clamp(0.2, 5.);
+    }
+
+    (y, next_h)
+}
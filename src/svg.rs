@@ -0,0 +1,132 @@
+use glam::Vec2;
+use std::fmt::Write;
+use crate::bodies::Body;
+use crate::canvas::*;
+use crate::images::ImageBoundary;
+use crate::quiver::Quiver;
+
+
+
+// Canvas which accumulates SVG elements. Coordinates and stroke weights are given in world
+// units and converted to pixels through the viewport.
+pub struct SvgCanvas {
+    viewport: Viewport,
+    elements: String
+}
+
+fn paint(color: Color) -> String {
+    format!(
+        "rgb({},{},{})",
+        (color.r*255.).round() as u8, (color.g*255.).round() as u8, (color.b*255.).round() as u8
+    )
+}
+
+impl SvgCanvas {
+    pub fn new(viewport: Viewport) -> Self {
+        Self { viewport: viewport, elements: String::new() }
+    }
+
+    pub fn background(&mut self, color: Color) {
+        writeln!(
+            self.elements,
+            r#"<rect width="100%" height="100%" fill="{}" fill-opacity="{}"/>"#,
+            paint(color), color.a
+        ).unwrap();
+    }
+
+    // Finish the document, consuming the canvas.
+    pub fn finish(self) -> String {
+        let (w, h) = (self.viewport.width, self.viewport.height);
+
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" "#,
+                r#"width="{}" height="{}" viewBox="0 0 {} {}">"#, "\n{}</svg>\n"
+            ),
+            w, h, w, h, self.elements
+        )
+    }
+
+    fn stroke(&self, weight: f32, color: Color) -> String {
+        format!(
+            r#"fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{:.2}""#,
+            paint(color), color.a, weight*self.viewport.scale
+        )
+    }
+
+    fn fill(&self, color: Color) -> String {
+        format!(r#"fill="{}" fill-opacity="{}""#, paint(color), color.a)
+    }
+
+    fn points(&self, points: &[Vec2]) -> String {
+        let mut s = String::new();
+
+        for p in points.iter().filter(|p| p.is_finite()) {
+            let p = self.viewport.to_pixel(*p);
+
+            write!(s, "{:.2},{:.2} ", p.x, p.y).unwrap();
+        }
+
+        s.pop();
+        s
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: Color) {
+        let c = self.viewport.to_pixel(center);
+
+        writeln!(
+            self.elements, r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" {}/>"#,
+            c.x, c.y, radius*self.viewport.scale, self.fill(color)
+        ).unwrap();
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: Color) {
+        let (a, b) = (self.viewport.to_pixel(start), self.viewport.to_pixel(end));
+
+        writeln!(
+            self.elements, r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {}/>"#,
+            a.x, a.y, b.x, b.y, self.stroke(weight, color)
+        ).unwrap();
+    }
+
+    fn tri(&mut self, points: [Vec2; 3], color: Color) {
+        writeln!(
+            self.elements, r#"<polygon points="{}" {}/>"#,
+            self.points(&points), self.fill(color)
+        ).unwrap();
+    }
+
+    fn polyline(&mut self, points: &[Vec2], weight: f32, color: Color) {
+        if points.len() < 2 { return; }
+
+        writeln!(
+            self.elements, r#"<polyline points="{}" {} stroke-linejoin="round"/>"#,
+            self.points(points), self.stroke(weight, color)
+        ).unwrap();
+    }
+}
+
+
+
+// Render the scene as the GUI would show it, on a black background, optionally with arrows
+// showing the field underneath the lines.
+pub fn render_svg(
+    viewport: Viewport,
+    bodies: &[Box<dyn Body>],
+    boundary: Option<&ImageBoundary>,
+    isopotentials: &[Vec<Vec2>],
+    field_lines: &[Vec<Vec2>],
+    magnetic_lines: &[Vec<Vec2>],
+    quiver: Option<&Quiver>
+) -> String {
+    let mut canvas = SvgCanvas::new(viewport);
+
+    canvas.background(BLACK);
+
+    if let Some(q) = quiver { q.draw(&mut canvas); }
+
+    draw_scene(&mut canvas, bodies, boundary, isopotentials, field_lines, magnetic_lines);
+    canvas.finish()
+}
@@ -1,5 +1,9 @@
-use nannou::geom::Vec2;
-use crate::bodies::Body;
+use glam::{Vec2, Vec3};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use crate::bodies::{Body, Field};
+use crate::collision::Shape;
+use crate::poisson::Grid;
 
 
 
@@ -24,7 +28,7 @@ fn rk4_2d(
     max_dl: f32,
     max_err: f32,
     max_steps: usize,
-    stop: impl Fn(Vec2, f32) -> bool
+    stop: impl Fn(Vec2, Vec2, f32) -> bool
 ) -> (Vec<Vec2>, StopReason) {
     let mut points = Vec::new();
     let mut r = r0;
@@ -59,6 +63,8 @@ fn rk4_2d(
             else { break; }
         }
         
+        let last = r;
+
         r += (k1+2.*(k2+k3)+k4)*dl/6.;
 
         if r.is_finite() { points.push(r); }
@@ -67,7 +73,7 @@ fn rk4_2d(
             break;
         }
         
-        if stop(r, dl) {
+        if stop(last, r, dl) {
             stop_reason = StopReason::StopCondition;
             break;
         }
@@ -76,39 +82,123 @@ fn rk4_2d(
     (points, stop_reason)
 }
 
+// Why a field line stopped, at either end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnd {
+    // On the surface of a charge, as Body::is_sink gives it
+    Charge,
+    // At the edge of the field, where it is NaN, such as a conducting boundary
+    Boundary,
+    // After the most steps allowed, usually far away or circling a point with no field
+    MaxSteps
+}
+
+// Steps are checked for charges at points no further apart than this, so that long steps
+// can't jump over small ones.
+const SINK_SPACING: f32 = 0.25;
+
+// The first point on the segment from a to b which is ended, checking points no further
+// than SINK_SPACING apart, and the point before it. Returns None if there is none.
+fn first_ended(a: Vec2, b: Vec2, ended: impl Fn(Vec2) -> bool) -> Option<(Vec2, Vec2)> {
+    let checks = (a.distance(b)/SINK_SPACING).ceil().max(1.) as usize;
+    let mut before = a;
+
+    for i in 1..=checks {
+        let p = a.lerp(b, i as f32/checks as f32);
+
+        if ended(p) { return Some((before, p)); }
+
+        before = p;
+    }
+
+    None
+}
+
+// Trace a field line through r0 both ways, along the field and against it. Lines stop on
+// the surface of any of the sinks' charges, or at the edge of the field, with their last
+// point found there by bisection. Returns the points, starting from the end the field
+// points towards, and why each end stopped, in the same order.
 pub fn field_line_points(
-    body: &impl Body,
+    body: &(impl Field + ?Sized),
+    sinks: &(impl Body + ?Sized),
     r0: Vec2,
     min_dl: f32,
     max_dl: f32,
     max_err: f32,
     max_steps: usize
-) -> Vec<Vec2> {
-    let f_forward = |r| body.e_field(r).normalize();
-    let f_reverse = |r| -body.e_field(r).normalize();
-    let stop = |r, _dl| body.potential(r).abs() > 5e3;
-    let (mut points, _) = rk4_2d(f_forward, r0, min_dl, max_dl, max_err, max_steps, stop);
-    let (rev_points, _) = rk4_2d(f_reverse, r0, min_dl, max_dl, max_err, max_steps, stop);
+) -> (Vec<Vec2>, [LineEnd; 2]) {
+    let ended = |r: Vec2| sinks.is_sink(r) || !body.e_field(r).is_finite();
+    // There is no direction to follow inside a charge or beyond the edge of the field,
+    // which shortens any step reaching into them rather than blowing up
+    let direction = |r: Vec2| {
+        if sinks.is_sink(r) { Vec2::ZERO } else { body.e_field(r).normalize_or_zero() }
+    };
+    // Only the end of each step is checked for the edge of the field, as that needs the
+    // field itself
+    let stop = |last: Vec2, r: Vec2, _dl| {
+        first_ended(last, r, |p| sinks.is_sink(p)).is_some() || !body.e_field(r).is_finite()
+    };
+    let trace = |sign: f32| {
+        let f = |r| sign*direction(r);
+        let (mut points, stop_reason) = rk4_2d(f, r0, min_dl, max_dl, max_err, max_steps, stop);
+
+        match stop_reason {
+            StopReason::StopCondition => {}
+            StopReason::MaxIterations => return (points, LineEnd::MaxSteps),
+            // A step which came out non-finite ran into the edge of the field without the
+            // stop condition seeing it first
+            StopReason::MathError => return (points, LineEnd::Boundary)
+        }
+
+        let r = points.pop().unwrap();
+        let last = points.last().copied().unwrap_or(r0);
+        let (mut outside, mut inside) = first_ended(last, r, ended).unwrap_or((last, r));
+
+        // Halve the gap down to the limit of precision
+        for _ in 0..24 {
+            let mid = (outside+inside)/2.;
+
+            if ended(mid) { inside = mid; } else { outside = mid; }
+        }
+
+        points.push(outside);
+        (points, if sinks.is_sink(inside) { LineEnd::Charge } else { LineEnd::Boundary })
+    };
+    let (mut points, forward_end) = trace(1.);
+    let (rev_points, reverse_end) = trace(-1.);
     
     points.reverse();
     points.push(r0);
-    points.extend(rev_points.into_iter());
+    points.extend(rev_points);
 
-    points
+    (points, [forward_end, reverse_end])
 }
 
 // Find points tracing an isopotential generated by a body. Returns these points and a bool
 // indicating whether the points form a closed loop.
 pub fn isopotential_points(
-    body: &impl Body,
+    body: &(impl Field + ?Sized),
     r0: Vec2,
     min_dl: f32,
     max_dl: f32,
     max_err: f32,
     max_steps: usize
 ) -> (Vec<Vec2>, bool) {
-    let f = |r| body.e_field(r).normalize().perp();
-    let stop = |r: Vec2, dl| (r-r0).length() < dl/2.;
+    follow_isopotential(body, r0, 1., min_dl, max_dl, max_err, max_steps)
+}
+
+// As isopotential_points, going round the isopotential the other way if sign is negative.
+fn follow_isopotential(
+    body: &(impl Field + ?Sized),
+    r0: Vec2,
+    sign: f32,
+    min_dl: f32,
+    max_dl: f32,
+    max_err: f32,
+    max_steps: usize
+) -> (Vec<Vec2>, bool) {
+    let f = |r| sign*body.e_field(r).normalize().perp();
+    let stop = |_last, r: Vec2, dl| (r-r0).length() < dl/2.;
     let (points, stop_reason) = rk4_2d(f, r0, min_dl, max_dl, max_err, max_steps, stop);
 
     (points, stop_reason == StopReason::StopCondition)
@@ -117,8 +207,8 @@ pub fn isopotential_points(
 // Divide an isopotential into segments sweeping out the given amount of flux. Returns a
 // vector of points on the isopotential dividing it in this way.
 pub fn divide_isopotential(
-    body: &impl Body,
-    isopotential: &Vec<Vec2>,
+    body: &(impl Field + ?Sized),
+    isopotential: &[Vec2],
     flux_step: f32
 ) -> Vec<Vec2> {
     let fields: Vec<f32> = isopotential.iter()
@@ -146,34 +236,270 @@ pub fn divide_isopotential(
 }
 
 
+// Trace a closed isopotential through r0 with the step settings used throughout the
+// program. If the isopotential closes, the first point is repeated at the end.
+pub fn trace_isopotential(body: &(impl Field + ?Sized), r0: Vec2) -> Vec<Vec2> {
+    let (mut points, is_loop) = isopotential_points(body, r0, 5e-3, 5., 1e-3, 1000);
+
+    if is_loop { points.push(points[0]); }
+
+    points
+}
+
+// Trace isopotentials through each starting point, spread across all cores. The result is
+// the same as tracing them one after another.
+pub fn trace_isopotentials(body: &(impl Field + ?Sized), starts: &[Vec2]) -> Vec<Vec<Vec2>> {
+    starts.par_iter().map(|r0| trace_isopotential(body, *r0)).collect()
+}
+
+// Points on every isopotential given where field lines should start, spaced so that each
+// pair of neighbouring lines encloses the given amount of flux.
+pub fn field_line_origins(
+    body: &(impl Field + ?Sized),
+    isopotentials: &[Vec<Vec2>],
+    flux_step: f32
+) -> Vec<Vec2> {
+    isopotentials.par_iter()
+                 .filter(|i| !i.is_empty())
+                 .flat_map_iter(|i| divide_isopotential(body, i, flux_step))
+                 .collect()
+}
+
+// Trace a field line from r0 with the step settings used throughout the program, ending on
+// the sinks' charges.
+pub fn trace_field_line(
+    body: &(impl Field + ?Sized),
+    sinks: &(impl Body + ?Sized),
+    r0: Vec2
+) -> (Vec<Vec2>, [LineEnd; 2]) {
+    field_line_points(body, sinks, r0, 5e-3, 5., 1e-3, 1000)
+}
+
+// Trace field lines from every isopotential given. The lines are traced in parallel, but
+// come out in the same order as tracing each isopotential's lines in turn.
+pub fn trace_field_lines(
+    body: &(impl Field + ?Sized),
+    sinks: &(impl Body + ?Sized),
+    isopotentials: &[Vec<Vec2>],
+    flux_step: f32
+) -> Vec<Vec<Vec2>> {
+    field_line_origins(body, isopotentials, flux_step)
+        .par_iter()
+        .map(|r0| trace_field_line(body, sinks, *r0).0)
+        .collect()
+}
+
+
+// Seeds for field lines straight from the bodies' charges, as Body::field_line_seeds gives
+// them, leaving out any inside another body. Returns the seeds next to positive charges,
+// and those next to negative ones along with the index of their body.
+pub fn charge_seeds(
+    bodies: &[Box<dyn Body>],
+    flux_step: f32
+) -> (Vec<Vec2>, Vec<(usize, Vec2)>) {
+    let mut positive = Vec::new();
+    let mut negative = Vec::new();
+
+    for (i, b) in bodies.iter().enumerate() {
+        for (p, sign) in b.field_line_seeds(flux_step) {
+            if bodies.is_sink(p) { continue; }
+
+            if sign > 0. { positive.push(p); } else { negative.push((i, p)); }
+        }
+    }
+
+    (positive, negative)
+}
+
+// The seeds of negative charges still needed once lines have been traced from the positive
+// ones. Every line arriving at a negative charge takes the place of the seed on that body
+// nearest to where it arrives, so lines joining two charges are only traced once, and each
+// charge still has as many lines as its seeds.
+pub fn unreached_seeds(
+    bodies: &[Box<dyn Body>],
+    seeds: &[(usize, Vec2)],
+    lines: &[(Vec<Vec2>, [LineEnd; 2])]
+) -> Vec<Vec2> {
+    let mut reached = vec![false; seeds.len()];
+
+    for (points, ends) in lines {
+        if ends[0] != LineEnd::Charge || points.len() < 2 { continue; }
+
+        // The line arrives at its first point, and stops just short of the charge there
+        let end = points[0];
+        let inside = end+1e-3*(points[0]-points[1]).normalize_or_zero();
+        let body = match bodies.iter().position(|b| b.is_sink(inside)) {
+            Some(i) => i,
+            None => continue
+        };
+        let nearest = (0..seeds.len())
+            .filter(|&k| seeds[k].0 == body && !reached[k])
+            .min_by(|&a, &b| end.distance(seeds[a].1).total_cmp(&end.distance(seeds[b].1)));
+
+        if let Some(k) = nearest { reached[k] = true; }
+    }
+
+    seeds.iter().zip(reached).filter(|(_, r)| !r).map(|((_, p), _)| *p).collect()
+}
+
+// Field lines traced straight from the bodies' charges, flux_step of flux apart, rather
+// than from isopotentials. Lines are traced from the positive charges first, and then from
+// the negative charges wherever none have arrived.
+pub fn auto_field_lines(
+    body: &(impl Field + ?Sized),
+    bodies: &[Box<dyn Body>],
+    flux_step: f32
+) -> Vec<Vec<Vec2>> {
+    let (sources, sinks) = charge_seeds(bodies, flux_step);
+    let trace = |seeds: &[Vec2]| -> Vec<(Vec<Vec2>, [LineEnd; 2])> {
+        seeds.par_iter().map(|r0| trace_field_line(body, bodies, *r0)).collect()
+    };
+    let mut lines = trace(&sources);
+    let rest = unreached_seeds(bodies, &sinks, &lines);
+
+    lines.extend(trace(&rest));
+    lines.into_iter().map(|(points, _)| points).collect()
+}
+
+
+// A number in [0, 1) which looks random but always comes out the same for the same grid
+// point and seed.
+pub(crate) fn hash_unit(i: i64, j: i64, seed: u64) -> f32 {
+    let mut h = (i as u64).wrapping_mul(0x9e3779b97f4a7c15)
+                          ^ (j as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+                          ^ seed;
+
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    (h >> 40) as f32/(1u64 << 24) as f32
+}
+
+
+// Move a point onto the isopotential at level z by Newton's method, stepping along the
+// field no more than max_step at a time. Returns None if it doesn't settle there.
+fn settle_on_level(
+    body: &(impl Field + ?Sized),
+    r0: Vec2,
+    z: f32,
+    max_step: f32
+) -> Option<Vec2> {
+    let mut r = r0;
+
+    for _ in 0..20 {
+        let e = body.e_field(r);
+        // The potential falls by E·δ over a small step δ
+        let step = ((body.potential(r)-z)*e/e.length_squared()).clamp_length_max(max_step);
+
+        if !step.is_finite() { return None; }
+
+        r += step;
+
+        if step.length() < 1e-4 { return Some(r); }
+    }
+
+    None
+}
+
+// Trace the isopotential through r0 as trace_isopotential does, but if it doesn't close, go
+// back and trace it the other way from r0 too, so that the whole of it is drawn.
+fn trace_isopotential_both_ways(body: &(impl Field + ?Sized), r0: Vec2) -> Vec<Vec2> {
+    let (mut points, is_loop) = follow_isopotential(body, r0, 1., 5e-3, 5., 1e-3, 1000);
+
+    if is_loop {
+        points.push(points[0]);
+        return points;
+    }
+
+    let (mut line, _) = follow_isopotential(body, r0, -1., 5e-3, 5., 1e-3, 1000);
+
+    line.reverse();
+    line.push(r0);
+    line.extend(points);
+    line
+}
+
+// Whether p is within distance of any part of a polyline.
+fn near_polyline(p: Vec2, line: &[Vec2], distance: f32) -> bool {
+    match line {
+        [] => false,
+        [a] => Shape::circle(*a, distance).contains(p),
+        _ => line.windows(2).any(|w| {
+            Shape { start: w[0], end: w[1], radius: distance }.contains(p)
+        })
+    }
+}
+
+// Trace isopotentials at each of the given levels, one for every separate piece of each
+// level crossing a grid, from samples of the potential on it. The pieces are found by
+// contouring the samples, and each is traced from a point on it moved exactly onto its
+// level, so the isopotentials are as fine as any traced ones rather than as coarse as the
+// grid. A piece lying on an isopotential already traced at its level, such as one which
+// leaves the grid and comes back, is only traced once.
+pub fn trace_isopotential_levels(
+    body: &(impl Field + ?Sized),
+    vals: &[f32],
+    grid: &Grid,
+    levels: &[f32]
+) -> Vec<Vec<Vec2>> {
+    levels.par_iter()
+          .flat_map_iter(|z| {
+              let mut traced: Vec<Vec<Vec2>> = Vec::new();
+
+              for piece in stitch_segments(&contour_lines(vals, grid, *z)) {
+                  let seed = match settle_on_level(body, piece[0], *z, grid.spacing) {
+                      Some(seed) => seed,
+                      None => continue
+                  };
+
+                  if traced.iter().any(|line| near_polyline(seed, line, grid.spacing/2.)) {
+                      continue;
+                  }
+
+                  traced.push(trace_isopotential_both_ways(body, seed));
+              }
+
+              traced
+          })
+          .collect()
+}
+
 
-/* THIS IS UNLIKELY TO BE USED BUT LEFT HERE IN CASE
+// Sample the potential at every node of a grid, row by row from the bottom left.
+pub fn sample_grid(body: &(impl Field + ?Sized), grid: &Grid) -> Vec<f32> {
+    (0..grid.ny).into_par_iter()
+                .flat_map_iter(|j| (0..grid.nx).map(move |i| {
+                    body.potential(grid.origin+grid.spacing*Vec2::new(i as f32, j as f32))
+                }))
+                .collect()
+}
 
 // Find line segments corresponding to a level curve of a given surface.
 // Parameters:
-//     vals - 2D array of surface heights, x index first, y index second
-//     xs   - x values that the x index corresponds to
-//     ys   - y values that the y index corresponds to
+//     vals - surface heights at the nodes of the grid, as sample_grid gives them
+//     grid - the grid the heights were sampled on
 //     z    - height of the level curve
 // Returns:
 //     A vector of line segments as (point, point) pairs, which together join
 //     up to form a contour. The order of the line segments is not contiguous.
-pub fn contour_lines(
-    vals: &ArrayView2<f32>,
-    xs: &ArrayView1<f32>,
-    ys: &ArrayView1<f32>,
-    z: f32
-) -> Vec<(Vec2, Vec2)> {
+pub fn contour_lines(vals: &[f32], grid: &Grid, z: f32) -> Vec<(Vec2, Vec2)> {
     let mut lines = Vec::new();
+    let node = |i: usize, j: usize| {
+        let r = grid.origin+grid.spacing*Vec2::new(i as f32, j as f32);
+
+        Vec3::new(r.x, r.y, vals[j*grid.nx+i])
+    };
+
+    for i_x in 0..grid.nx.saturating_sub(1) {
+        for i_y in 0..grid.ny.saturating_sub(1) {
+            let r_00 = node(i_x, i_y);
+            let r_10 = node(i_x+1, i_y);
+            let r_01 = node(i_x, i_y+1);
+            let r_11 = node(i_x+1, i_y+1);
+
+            // Cells touching a singularity or the edge of the field are left out
+            if ![r_00, r_10, r_01, r_11].iter().all(|r| r.z.is_finite()) { continue; }
 
-    for i_x in 0..vals.nrows()-1 {
-        for i_y in 0..vals.ncols()-1 {
-            let (x0, y0) = (xs[i_x], ys[i_y]);
-            let (x1, y1) = (xs[i_x+1], ys[i_y+1]);
-            let r_00 = Vec3::new(x0, y0, vals[[i_x, i_y]]);
-            let r_10 = Vec3::new(x1, y0, vals[[i_x+1, i_y]]);
-            let r_01 = Vec3::new(x0, y1, vals[[i_x, i_y+1]]);
-            let r_11 = Vec3::new(x1, y1, vals[[i_x+1, i_y+1]]);
             let r_avg = (r_00+r_10+r_01+r_11)/4.;
 
             let t0 = triangle_intersection(r_00, r_10, r_avg, z);
@@ -201,22 +527,13 @@ fn triangle_intersection(r0: Vec3, r1: Vec3, r2: Vec3, z: f32) -> Option<(Vec2,
     let inter12 = line_intersection(r1, r2, z);
     let inter02 = line_intersection(r0, r2, z);
 
-    if let Some(inter01) = inter01 {
-        if let Some(inter12) = inter12 {
-            if inter02 == None { Some((inter01, inter12)) }
-            else { None }
-        }
-        else {
-            if let Some(inter02) = inter02 { Some((inter01, inter02)) }
-            else { None }
-        }
-    }
-    else {
-        if let Some(inter12) = inter12 {
-            if let Some(inter02) = inter02 { Some((inter12, inter02)) }
-            else { None }
-        }
-        else { None }
+    // A vertex exactly on the plane counts as above it, so the plane always crosses
+    // either none of the edges or two of them. Both can meet at that vertex, giving a
+    // segment of no length which is dropped.
+    match (inter01, inter12, inter02) {
+        (Some(a), Some(b), None) | (Some(a), None, Some(b)) | (None, Some(a), Some(b))
+            if a != b => Some((a, b)),
+        _ => None
     }
 }
 
@@ -228,11 +545,68 @@ fn triangle_intersection(r0: Vec3, r1: Vec3, r2: Vec3, z: f32) -> Option<(Vec2,
 // Returns:
 //     Optionally the intersection point, if it exists.
 fn line_intersection(r0: Vec3, r1: Vec3, z: f32) -> Option<Vec2> {
-    if (r0[2] < z && r1[2] < z) | (r0[2] > z && r1[2] > z) { None }
-    else if r0[2] == z && r1[2] == z { Some(r0.lerp(r1, 0.5).xy()) }
-    else {
-        let frac = (z-r0[2])/(r1[2]-r0[2]);
-        
-        Some(r0.lerp(r1, frac).xy())
+    if (r0.z < z) == (r1.z < z) { return None; }
+
+    // Always interpolate from the same end, so that the cells either side of an edge find
+    // exactly the same point on it
+    let (r0, r1) = if (r0.x, r0.y) < (r1.x, r1.y) { (r0, r1) } else { (r1, r0) };
+    let frac = (z-r0.z)/(r1.z-r0.z);
+
+    Some(r0.lerp(r1, frac).truncate())
+}
+
+// Join line segments which share ends into polylines. A polyline which closes on itself
+// repeats its first point at the end, as trace_isopotential does.
+pub fn stitch_segments(segments: &[(Vec2, Vec2)]) -> Vec<Vec<Vec2>> {
+    let key = |p: Vec2| (p.x.to_bits(), p.y.to_bits());
+    let mut ends: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    let mut used = vec![false; segments.len()];
+    let mut polylines = Vec::new();
+
+    for (i, (a, b)) in segments.iter().enumerate() {
+        ends.entry(key(*a)).or_default().push(i);
+        ends.entry(key(*b)).or_default().push(i);
     }
-}*/
+
+    // Follow unused segments on from the end of a polyline for as long as there are any
+    let extend = |line: &mut Vec<Vec2>, used: &mut [bool]| {
+        while let Some(&end) = line.last() {
+            let i = match ends[&key(end)].iter().copied().find(|&i| !used[i]) {
+                Some(i) => i,
+                None => break
+            };
+            let (a, b) = segments[i];
+
+            used[i] = true;
+            line.push(if key(a) == key(end) { b } else { a });
+        }
+    };
+
+    for (i, (a, b)) in segments.iter().enumerate() {
+        if used[i] { continue; }
+
+        let mut line = vec![*a, *b];
+
+        used[i] = true;
+        extend(&mut line, &mut used);
+
+        // An open polyline may also go on from where it started
+        if key(line[0]) != key(line[line.len()-1]) {
+            line.reverse();
+            extend(&mut line, &mut used);
+        }
+
+        polylines.push(line);
+    }
+
+    polylines
+}
+
+// Contour the potential at each of the given levels from samples on a grid, giving the
+// isopotentials as polylines. Unlike tracing, this finds every piece of every level in the
+// grid, wherever it is, though only as finely as the grid.
+pub fn contour_isopotentials(vals: &[f32], grid: &Grid, levels: &[f32]) -> Vec<Vec<Vec2>> {
+    levels.par_iter()
+          .flat_map_iter(|z| stitch_segments(&contour_lines(vals, grid, *z)))
+          .collect()
+}
@@ -0,0 +1,116 @@
+// Barnes-Hut fields and forces against direct summation.
+
+use fieldsim::bodies::*;
+use fieldsim::quadtree::Quadtree;
+use fieldsim::simulation::Simulator;
+use fieldsim::Vec2;
+
+
+
+// Small deterministic generator, so that the tests don't depend on a random number crate.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32/(1u64 << 24) as f32
+    }
+
+    fn point(&mut self, size: f32) -> Vec2 {
+        Vec2::new(self.next()-0.5, self.next()-0.5)*size
+    }
+}
+
+fn charges(n: usize, rng: &mut Lcg) -> Vec<Box<dyn Body>> {
+    let mut bodies: Vec<Box<dyn Body>> = (0..n).map(|_| {
+                                                   Box::new(PointCharge {
+                                                       charge: 2.*rng.next()-1.,
+                                                       mass: 1.,
+                                                       pos: rng.point(1000.),
+                                                       vel: Vec2::ZERO
+                                                   }) as Box<dyn Body>
+                                               })
+                                               .collect();
+
+    bodies.push(Box::new(Dipole::new(5., 1., 0.3, rng.point(1000.), Vec2::ZERO)));
+    bodies.push(Box::new(LineCharge::new(rng.point(1000.), rng.point(1000.), 20.)));
+    bodies.push(Box::new(GlobalField { field: Vec2::new(0.01, 0.) }));
+    bodies
+}
+
+// Root mean square of the errors over the root mean square of the exact values.
+fn relative_error(exact: &[Vec2], approx: &[Vec2]) -> f32 {
+    let error: f32 = exact.iter().zip(approx).map(|(e, a)| e.distance_squared(*a)).sum();
+    let size: f32 = exact.iter().map(|e| e.length_squared()).sum();
+
+    (error/size).sqrt()
+}
+
+
+
+#[test]
+fn field_matches_direct_sum() {
+    let mut rng = Lcg(1);
+    let bodies = charges(2000, &mut rng);
+    let points: Vec<Vec2> = (0..500).map(|_| rng.point(1200.)).collect();
+    let exact_e: Vec<Vec2> = points.iter().map(|p| bodies.e_field(*p)).collect();
+    let exact_v: Vec<Vec2> = points.iter().map(|p| Vec2::X*bodies.potential(*p)).collect();
+
+    for (theta, tolerance) in [(0.3, 1e-4), (0.5, 1e-3), (0.7, 1e-2)] {
+        let tree = Quadtree::new(&bodies, theta);
+        let e: Vec<Vec2> = points.iter().map(|p| tree.e_field(*p)).collect();
+        let v: Vec<Vec2> = points.iter().map(|p| Vec2::X*tree.potential(*p)).collect();
+        let (e_error, v_error) = (relative_error(&exact_e, &e), relative_error(&exact_v, &v));
+
+        println!(
+            "opening angle {}: field error {:.2e}, potential error {:.2e}",
+            theta, e_error, v_error
+        );
+
+        assert!(e_error < tolerance, "field error {} at opening angle {}", e_error, theta);
+        assert!(v_error < tolerance, "potential error {} at opening angle {}", v_error, theta);
+    }
+}
+
+#[test]
+fn magnetic_field_matches_direct_sum() {
+    let mut rng = Lcg(2);
+    let bodies: Vec<Box<dyn Body>> = (0..1000).map(|_| {
+                                                   Box::new(CurrentWire {
+                                                       current: 2.*rng.next()-1.,
+                                                       mass: 1.,
+                                                       pos: rng.point(1000.),
+                                                       vel: Vec2::ZERO
+                                                   }) as Box<dyn Body>
+                                               })
+                                               .collect();
+    let points: Vec<Vec2> = (0..500).map(|_| rng.point(1200.)).collect();
+    let tree = Quadtree::new(&bodies, 0.5);
+    let exact: Vec<Vec2> = points.iter().map(|p| bodies.b_field(*p).truncate()).collect();
+    let approx: Vec<Vec2> = points.iter().map(|p| tree.b_field(*p).truncate()).collect();
+    let error = relative_error(&exact, &approx);
+
+    println!("magnetic field error {:.2e}", error);
+
+    assert!(error < 1e-3, "magnetic field error {}", error);
+}
+
+#[test]
+fn forces_match_direct_sum() {
+    let mut direct = charges(1000, &mut Lcg(3));
+    let mut approx = charges(1000, &mut Lcg(3));
+    let mut exact_sim = Simulator::default();
+    let mut tree_sim = Simulator::default();
+
+    tree_sim.opening_angle = Some(0.5);
+    exact_sim.run(&mut direct, 1);
+    tree_sim.run(&mut approx, 1);
+
+    let exact: Vec<Vec2> = direct.iter().map(|b| b.kinematics().vel).collect();
+    let tree: Vec<Vec2> = approx.iter().map(|b| b.kinematics().vel).collect();
+    let error = relative_error(&exact, &tree);
+
+    println!("force error {:.2e}", error);
+
+    assert!(error < 1e-3, "force error {}", error);
+}
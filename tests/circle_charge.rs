@@ -0,0 +1,51 @@
+// The potential of a circle charge against its closed form, and against its own field.
+
+use fieldsim::bodies::*;
+use fieldsim::Vec2;
+
+
+
+fn circle() -> CircleCharge {
+    CircleCharge { charge: 3., mass: 1., radius: 2., pos: Vec2::new(1., -1.), vel: Vec2::ZERO }
+}
+
+// -q ln r outside, and inside the potential of the linear field, meeting it at the radius.
+fn analytic(c: &CircleCharge, pos: Vec2) -> f32 {
+    let r = pos.distance(c.pos);
+
+    if r >= c.radius { -c.charge*r.ln() }
+    else { -c.charge*(c.radius.ln()+(r*r/(c.radius*c.radius)-1.)/2.) }
+}
+
+
+
+#[test]
+fn potential_matches_closed_form() {
+    let c = circle();
+
+    for r in [0.1, 0.5, 1., 1.9, 2., 2.1, 5., 50.] {
+        for angle in [0., 1., 2.5, 4.] {
+            let pos = c.pos+r*Vec2::new(f32::cos(angle), f32::sin(angle));
+            let (v, exact) = (c.potential(pos), analytic(&c, pos));
+
+            assert!((v-exact).abs() < 1e-4*exact.abs().max(1.), "r = {}: {} != {}", r, v, exact);
+        }
+    }
+}
+
+#[test]
+fn field_is_minus_gradient_of_potential() {
+    let c = circle();
+    let h = 1e-2;
+
+    for pos in [Vec2::new(1.5, -0.5), Vec2::new(0., 0.), Vec2::new(4., 2.), Vec2::new(-6., 1.)] {
+        let grad = Vec2::new(
+            c.potential(pos+Vec2::X*h)-c.potential(pos-Vec2::X*h),
+            c.potential(pos+Vec2::Y*h)-c.potential(pos-Vec2::Y*h)
+        )/(2.*h);
+
+        let e = c.e_field(pos);
+
+        assert!((e+grad).length() < 1e-2, "at {}: {} vs {}", pos, e, -grad);
+    }
+}
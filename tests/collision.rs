@@ -0,0 +1,111 @@
+// Contact between solid bodies, which bounce or merge.
+
+use fieldsim::bodies::*;
+use fieldsim::collision::{self, Collisions};
+use fieldsim::Vec2;
+
+
+
+// Neither rod's ends touch the other, so contact can only be found between their middles.
+// The cheapest way apart is to lift the vertical rod clear of the horizontal one.
+#[test]
+fn crossing_rods_are_pushed_apart() {
+    let mut bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(LineCharge::new(Vec2::new(-4., 0.), Vec2::new(4., 0.), 0.)
+                     .with_motion(1., Vec2::ZERO, 0.)),
+        Box::new(LineCharge::new(Vec2::new(0.5, -1.5), Vec2::new(0.5, 6.), 0.)
+                     .with_motion(1., Vec2::ZERO, 0.))
+    ];
+
+    collision::resolve(&mut bodies, &Collisions::default());
+
+    let (a, b) = (bodies[0].shape().unwrap(), bodies[1].shape().unwrap());
+
+    assert!((a.start.y+1.75).abs() < 1e-5 && (a.end.y+1.75).abs() < 1e-5, "{:?}", a);
+    assert!((b.start.y-0.25).abs() < 1e-5, "{:?}", b);
+    assert!((b.start.x-0.5).abs() < 1e-5 && (b.end.x-0.5).abs() < 1e-5, "{:?}", b);
+}
+
+fn circle(charge: f32, mass: f32, radius: f32, pos: Vec2, vel: Vec2) -> Box<dyn Body> {
+    Box::new(CircleCharge { charge, mass, radius, pos, vel })
+}
+
+// Along the line between their centres the bodies' relative velocity is reversed and scaled
+// by the restitution, while across it nothing changes. Momentum is kept throughout.
+#[test]
+fn bounce_follows_the_restitution() {
+    for restitution in [1., 0.5, 0.] {
+        let mut bodies = vec![
+            circle(0., 1., 1., Vec2::new(-0.9, 0.), Vec2::new(2., 0.5)),
+            circle(0., 3., 1., Vec2::new(0.9, 0.), Vec2::new(-1., -0.25))
+        ];
+        let settings = Collisions { restitution, merge: false };
+
+        collision::resolve(&mut bodies, &settings);
+
+        let (a, b) = (bodies[0].kinematics(), bodies[1].kinematics());
+        let momentum = a.vel+3.*b.vel;
+
+        assert_eq!(bodies.len(), 2);
+        assert!((b.vel.x-a.vel.x-3.*restitution).abs() < 1e-5, "{} {}", a.vel, b.vel);
+        assert!(momentum.distance(Vec2::new(-1., -0.25)) < 1e-5, "{}", momentum);
+        assert!(a.vel.y == 0.5 && b.vel.y == -0.25, "{} {}", a.vel, b.vel);
+        assert!(b.pos.x-a.pos.x >= 2.-1e-5, "{} {}", a.pos, b.pos);
+    }
+}
+
+// Merged bodies keep the total charge, mass and momentum, and sit at their centre of mass.
+#[test]
+fn point_charges_merge() {
+    let mut bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::ZERO, vel: Vec2::X }),
+        Box::new(PointCharge { charge: -2., mass: 3., pos: Vec2::new(0.1, 0.), vel: Vec2::Y })
+    ];
+
+    collision::resolve(&mut bodies, &Collisions { restitution: 1., merge: true });
+
+    let k = bodies[0].kinematics();
+
+    assert_eq!(bodies.len(), 1);
+    assert_eq!((bodies[0].charge(), bodies[0].mass()), (-1., 4.));
+    assert!(k.pos.distance(Vec2::new(0.075, 0.)) < 1e-6, "{}", k.pos);
+    assert!(k.vel.distance(Vec2::new(0.25, 0.75)) < 1e-6, "{}", k.vel);
+}
+
+// A circle charge absorbs whatever it can, even listed second, and keeps the total area.
+#[test]
+fn circle_charges_absorb_circles_and_points() {
+    let mut bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::new(2., 0.), vel: Vec2::ZERO }),
+        circle(2., 2., 3., Vec2::ZERO, Vec2::X),
+        circle(-4., 1., 4., Vec2::new(0., 5.), Vec2::ZERO)
+    ];
+
+    collision::resolve(&mut bodies, &Collisions { restitution: 1., merge: true });
+
+    let total = bodies[0].kinematics();
+
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0].shape().unwrap().radius, 5.);
+    assert_eq!((bodies[0].charge(), bodies[0].mass()), (-1., 4.));
+    assert!(total.vel.distance(Vec2::new(0.5, 0.)) < 1e-6, "{}", total.vel);
+    assert!(total.pos.distance(Vec2::new(0.5, 1.25)) < 1e-5, "{}", total.pos);
+}
+
+// Bodies which can't be absorbed bounce off instead, here swapping velocities as equal
+// masses do.
+#[test]
+fn rods_bounce_off_point_charges() {
+    let mut bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(LineCharge::new(Vec2::new(-4., 0.), Vec2::new(4., 0.), 1.)
+                     .with_motion(1., Vec2::ZERO, 0.)),
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::new(0., 0.5), vel: -Vec2::Y })
+    ];
+
+    collision::resolve(&mut bodies, &Collisions { restitution: 1., merge: true });
+
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0].charge()+bodies[1].charge(), 2.);
+    assert!(bodies[0].kinematics().vel.distance(-Vec2::Y) < 1e-6);
+    assert!(bodies[1].kinematics().vel.length() < 1e-6);
+}
@@ -0,0 +1,74 @@
+// Boundary element conductors against the closed-form solution for a conducting circle
+// near a point charge.
+
+use fieldsim::bodies::*;
+use fieldsim::conductor::{Condition, Conductor, Electrode, Outline};
+use fieldsim::Vec2;
+use std::f32::consts::TAU;
+
+
+
+// A charge q a distance D from the centre of a conducting circle of radius a is imaged by
+// -q at a²/D and +q at the centre. With no net charge the circle is then at the potential
+// the charge alone has at its centre, -q ln D, and holding it at V instead adds a charge
+// of (-q ln D-V)/ln a.
+const Q: f32 = 1.;
+const D: f32 = 12.;
+const A: f32 = 5.;
+
+fn circle(condition: Condition) -> Conductor {
+    let electrode = Electrode {
+        outline: Outline::Circle { center: Vec2::ZERO, radius: A },
+        condition
+    };
+    let mut conductor = Conductor::new(vec![electrode], 128);
+
+    conductor.induce(&charge());
+    conductor
+}
+
+fn charge() -> PointCharge {
+    PointCharge { charge: Q, mass: 1., pos: Vec2::new(D, 0.), vel: Vec2::ZERO }
+}
+
+// Potential of the conductor and the charge together, on and just inside the surface and
+// at the centre, all of which should be the conductor's potential.
+fn potentials(conductor: &Conductor) -> Vec<f32> {
+    let total = |p: Vec2| conductor.potential(p)+charge().potential(p);
+    let mut v: Vec<f32> = (0..90).flat_map(|i| {
+                                     let dir = Vec2::new((TAU*i as f32/90.).cos(),
+                                                         (TAU*i as f32/90.).sin());
+
+                                     [total(A*dir), total(0.95*A*dir)]
+                                 })
+                                 .collect();
+
+    v.push(total(Vec2::ZERO));
+    v
+}
+
+#[test]
+fn held_surface_is_an_equipotential() {
+    let conductor = circle(Condition::Potential(5.));
+
+    for v in potentials(&conductor) {
+        assert!((v-5.).abs() < 1e-2, "{}", v);
+    }
+
+    let expected = (-Q*D.ln()-5.)/A.ln();
+
+    assert!((conductor.charge()-expected).abs() < 1e-2*expected.abs(), "{}", conductor.charge());
+}
+
+#[test]
+fn floating_conductor_keeps_its_charge() {
+    let conductor = circle(Condition::Floating(0.));
+    let v = conductor.potentials()[0];
+
+    assert!(conductor.charge().abs() < 1e-4, "{}", conductor.charge());
+    assert!((v+Q*D.ln()).abs() < 1e-2, "{}", v);
+
+    for p in potentials(&conductor) {
+        assert!((p-v).abs() < 1e-2, "{} against {}", p, v);
+    }
+}
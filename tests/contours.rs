@@ -0,0 +1,101 @@
+// Isopotentials found by contouring samples of the potential on a grid.
+
+use fieldsim::bodies::*;
+use fieldsim::heatmap::{HeatmapScale, HeatmapStyle, MAX_LEVELS};
+use fieldsim::poisson::Grid;
+use fieldsim::util;
+use fieldsim::Vec2;
+
+
+
+fn charge(q: f32, pos: Vec2) -> Box<dyn Body> {
+    Box::new(PointCharge { charge: q, mass: 1., pos, vel: Vec2::ZERO })
+}
+
+fn is_closed(line: &[Vec2]) -> bool {
+    line.len() > 2 && line[0].distance(line[line.len()-1]) < 1e-3
+}
+
+fn centroid(line: &[Vec2]) -> Vec2 {
+    line.iter().fold(Vec2::ZERO, |sum, p| sum+*p)/line.len() as f32
+}
+
+// Contouring the potential of a lone charge gives a single circle, stitched into one
+// polyline which ends exactly where it started.
+#[test]
+fn a_closed_contour_stitches_into_one_loop() {
+    let bodies = [charge(1., Vec2::new(0.3, -0.2))];
+    let grid = Grid::covering(Vec2::splat(-5.), Vec2::splat(5.), 0.1);
+    let vals = util::sample_grid(&bodies[..], &grid);
+    let level = bodies[..].potential(Vec2::new(2.3, -0.2));
+    let lines = util::contour_isopotentials(&vals, &grid, &[level]);
+
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0][0], lines[0][lines[0].len()-1]);
+    assert!(lines[0].iter().all(|p| (p.distance(Vec2::new(0.3, -0.2))-2.).abs() < 0.05));
+}
+
+// A circle running off the side of the grid is left open, with both ends on the edge it
+// crosses, rather than being joined up across the gap.
+#[test]
+fn a_contour_leaving_the_grid_stays_open() {
+    let bodies = [charge(1., Vec2::ZERO)];
+    let grid = Grid::covering(Vec2::splat(-5.), Vec2::new(1., 5.), 0.1);
+    let vals = util::sample_grid(&bodies[..], &grid);
+    let level = bodies[..].potential(Vec2::new(2., 0.));
+    let lines = util::contour_isopotentials(&vals, &grid, &[level]);
+
+    assert_eq!(lines.len(), 1);
+
+    let (first, last) = (lines[0][0], lines[0][lines[0].len()-1]);
+    let right = grid.end().x;
+
+    assert!((first.x-right).abs() < 1e-4 && (last.x-right).abs() < 1e-4, "{} {}", first, last);
+    assert!(first.y*last.y < 0.);
+}
+
+// The level of a circle of radius 1 around either of two like charges 10 apart is in two
+// separate pieces, each of which is traced once, all the way round.
+#[test]
+fn separate_pieces_of_a_level_are_traced_separately() {
+    let bodies = [charge(1., Vec2::new(-5., 0.)), charge(1., Vec2::new(5., 0.))];
+    let grid = Grid::covering(Vec2::splat(-10.), Vec2::splat(10.), 0.1);
+    let vals = util::sample_grid(&bodies[..], &grid);
+    let level = bodies[..].potential(Vec2::new(-4., 0.));
+    let lines = util::trace_isopotential_levels(&bodies[..], &vals, &grid, &[level]);
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|l| is_closed(l)));
+
+    let mut centres: Vec<f32> = lines.iter().map(|l| centroid(&l[1..]).x).collect();
+
+    centres.sort_by(f32::total_cmp);
+    assert!((centres[0]+5.).abs() < 0.1 && (centres[1]-5.).abs() < 0.1, "{:?}", centres);
+}
+
+// A grid narrower than a circular isopotential cuts it into two arcs, which are both part
+// of the same closed curve, so it is only traced once.
+#[test]
+fn pieces_of_one_isopotential_are_traced_once() {
+    let bodies = [charge(1., Vec2::ZERO)];
+    let grid = Grid::covering(Vec2::new(-1., -5.), Vec2::new(1., 5.), 0.1);
+    let vals = util::sample_grid(&bodies[..], &grid);
+    let level = bodies[..].potential(Vec2::new(3., 0.));
+
+    assert_eq!(util::contour_isopotentials(&vals, &grid, &[level]).len(), 2);
+
+    let lines = util::trace_isopotential_levels(&bodies[..], &vals, &grid, &[level]);
+
+    assert_eq!(lines.len(), 1);
+    assert!(is_closed(&lines[0]));
+    assert!(lines[0].iter().all(|p| (p.length()-3.).abs() < 1e-2));
+}
+
+#[test]
+fn level_steps_giving_too_many_levels_are_refused() {
+    let scale = HeatmapScale { reference: 0., range: 10., style: HeatmapStyle::default() };
+
+    assert_eq!(scale.levels_every(4.).unwrap(), vec![-8., -4., 0., 4., 8.]);
+    assert!(scale.levels_every(20./MAX_LEVELS as f32).is_err());
+    assert!(scale.levels_every(0.).is_err());
+}
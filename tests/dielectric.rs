@@ -0,0 +1,65 @@
+// Dielectric regions against the closed-form field of a dielectric circle in a uniform
+// field.
+
+use fieldsim::bodies::*;
+use fieldsim::conductor::Outline;
+use fieldsim::dielectric::{Dielectric, Region};
+use fieldsim::Vec2;
+use std::f32::consts::TAU;
+
+
+
+const EPS: f32 = 3.;
+const RADIUS: f32 = 5.;
+
+fn applied() -> GlobalField {
+    GlobalField { field: Vec2::new(1., 0.5) }
+}
+
+fn circle() -> Dielectric {
+    let region = Region {
+        outline: Outline::Circle { center: Vec2::ZERO, radius: RADIUS },
+        permittivity: EPS
+    };
+    let mut dielectric = Dielectric::new(vec![region], 256);
+
+    dielectric.induce(&applied());
+    dielectric
+}
+
+// Inside a dielectric circle an applied uniform field is weakened evenly, to 2/(ε_r+1) of
+// its strength.
+#[test]
+fn field_inside_a_circle_is_uniform() {
+    let dielectric = circle();
+    let expected = applied().field*2./(EPS+1.);
+
+    for r in [0., 1., 2.5, 4.] {
+        for i in 0..8 {
+            let theta = TAU*i as f32/8.;
+            let pos = r*Vec2::new(theta.cos(), theta.sin());
+            let e = applied().field+dielectric.e_field(pos);
+
+            assert!(e.distance(expected) < 1e-2*expected.length(), "{} at {}", e, pos);
+        }
+    }
+}
+
+// Outside, the circle adds the field of a line dipole, so that just beyond the surface the
+// normal field is ε_r times that just inside, as continuity of D = ε_r E requires.
+#[test]
+fn field_outside_a_circle_is_the_applied_field_and_a_dipole() {
+    let dielectric = circle();
+    let (e0, k) = (applied().field, (EPS-1.)/(EPS+1.));
+
+    for r in [5.05, 6., 10., 20.] {
+        for i in 0..16 {
+            let theta = TAU*(i as f32+0.25)/16.;
+            let n = Vec2::new(theta.cos(), theta.sin());
+            let expected = e0+k*RADIUS*RADIUS/(r*r)*(2.*e0.dot(n)*n-e0);
+            let e = e0+dielectric.e_field(r*n);
+
+            assert!(e.distance(expected) < 1e-2*e0.length(), "{} at {}", e, r*n);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Dipoles moving and turning as rigid bodies in external fields.
+
+use fieldsim::bodies::*;
+use fieldsim::Vec2;
+use std::f32::consts::PI;
+
+
+
+// Moment of inertia of the two charges, each carrying half the mass half a unit from the
+// centre.
+fn inertia(mass: f32) -> f32 {
+    2.*(mass/2.)*0.25
+}
+
+// A uniform field turns a dipole with the torque p x E, without pushing it anywhere.
+#[test]
+fn torque_is_p_cross_e() {
+    let field = GlobalField { field: Vec2::new(0., 1.5) };
+    let dipole = Dipole::new(2., 3., 0.3, Vec2::new(1., 2.), Vec2::ZERO);
+    let (force, torque) = dipole.force_torque(&field);
+
+    assert!((torque-dipole.dipole_moment().perp_dot(field.field)).abs() < 1e-5);
+    assert!(force.length() < 1e-5);
+    assert!((dipole.inertia()-inertia(3.)).abs() < 1e-6);
+}
+
+// Turned a little way from the field, a dipole swings about alignment with the angular
+// frequency sqrt(pE/I), reaching the opposite angle after half a period.
+#[test]
+fn dipole_swings_about_alignment() {
+    let field = GlobalField { field: Vec2::new(2., 0.) };
+    let mut dipole = Dipole::new(1., 1., 0.1, Vec2::ZERO, Vec2::ZERO);
+    let half_period = PI/(2./inertia(1.)).sqrt();
+    let steps = 2000;
+    let h = half_period/steps as f32;
+
+    for _ in 0..steps {
+        let mut k = dipole.kinematics();
+
+        k.ang_vel += h*dipole.force_torque(&field).1/dipole.inertia();
+        k.angle += h*k.ang_vel;
+        dipole.set_kinematics(k);
+    }
+
+    let angle = dipole.kinematics().angle;
+
+    assert!((angle+0.1).abs() < 2e-3, "angle {}", angle);
+}
+
+// Dipoles lined up head to tail attract, and side by side and parallel they repel.
+#[test]
+fn dipoles_attract_head_to_tail_and_repel_side_by_side() {
+    for (offset, attract) in [(Vec2::new(5., 0.), true), (Vec2::new(0., 5.), false)] {
+        let a = Dipole::new(1., 1., 0., Vec2::ZERO, Vec2::ZERO);
+        let b = Dipole::new(1., 1., 0., offset, Vec2::ZERO);
+        let (force, _) = a.force_torque(&b);
+
+        assert_eq!(force.dot(offset) > 0., attract, "{} with the other at {}", force, offset);
+    }
+}
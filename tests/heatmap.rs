@@ -0,0 +1,71 @@
+// Sampling the potential over a viewport and colouring it.
+
+use fieldsim::bodies::*;
+use fieldsim::canvas::Viewport;
+use fieldsim::heatmap::{self, Colormap, HeatmapScale, HeatmapStyle};
+use fieldsim::Vec2;
+
+
+
+#[test]
+fn samples_are_taken_at_pixel_centres_row_by_row() {
+    let charge = PointCharge { charge: 1., mass: 1., pos: Vec2::new(3., -2.), vel: Vec2::ZERO };
+    let viewport = Viewport { center: Vec2::new(1., 1.), scale: 0.5, width: 5, height: 3 };
+    let values = heatmap::sample_potential(&charge, &viewport);
+
+    assert_eq!(values.len(), 15);
+
+    for (k, v) in values.iter().enumerate() {
+        let pixel = Vec2::new((k%5) as f32+0.5, (k/5) as f32+0.5);
+
+        assert_eq!(*v, charge.potential(viewport.to_world(pixel)));
+    }
+}
+
+// A sample landing right on a charge is infinite, which neither stretches the range nor
+// blanks the pixel, while NaN, where there is no field, is left transparent.
+#[test]
+fn infinite_potentials_take_the_end_colour() {
+    let mut values: Vec<f32> = (0..100).map(|i| i as f32).collect();
+
+    values.extend([f32::INFINITY, f32::NEG_INFINITY, f32::NAN]);
+
+    let scale = HeatmapScale::fit(&values, HeatmapStyle::default());
+
+    assert!(scale.reference.is_finite() && scale.range.is_finite() && scale.range < 60.);
+    assert_eq!(scale.color(f32::INFINITY), scale.color(scale.reference+scale.range));
+    assert_eq!(scale.color(f32::NEG_INFINITY), scale.color(scale.reference-scale.range));
+    assert_eq!(scale.color(f32::NAN).a, 0.);
+}
+
+// Diverging colours are black at the reference, red above it and blue below.
+#[test]
+fn diverging_map_is_centred_on_the_reference() {
+    let style = HeatmapStyle { reference: Some(2.), ..HeatmapStyle::default() };
+    let scale = HeatmapScale::fit(&[0., 1., 2., 3., 4.], style);
+    let (above, below) = (scale.color(3.), scale.color(1.));
+
+    assert_eq!(scale.reference, 2.);
+    assert_eq!((scale.color(2.).r, scale.color(2.).b), (0., 0.));
+    assert!(above.r > 0. && above.b == 0. && below.b > 0. && below.r == 0.);
+}
+
+// Banded maps give one flat colour to each band, with every band used. The log magnitude
+// map is left out, as it gives bands the same distance either side of the reference the
+// same colour.
+#[test]
+fn bands_are_flat() {
+    for colormap in [Colormap::Diverging, Colormap::Sequential] {
+        let style = HeatmapStyle { colormap, bands: Some(4), reference: Some(0.) };
+        let scale = HeatmapScale { reference: 0., range: 1., style };
+        let mut colors: Vec<[u32; 3]> = (0..=200).map(|i| {
+                                                    let c = scale.color(i as f32/100.-1.);
+
+                                                    [c.r, c.g, c.b].map(f32::to_bits)
+                                                })
+                                                .collect();
+
+        colors.dedup();
+        assert_eq!(colors.len(), 4, "{}", colormap.name());
+    }
+}
@@ -0,0 +1,102 @@
+// Image boundaries, whose images should make the boundary an equipotential.
+
+use fieldsim::bodies::*;
+use fieldsim::conductor::Condition;
+use fieldsim::images::ImageBoundary;
+use fieldsim::Vec2;
+use std::f32::consts::TAU;
+
+
+
+fn bodies() -> Vec<Box<dyn Body>> {
+    vec![
+        Box::new(PointCharge { charge: 3., mass: 1., pos: Vec2::new(-4., 6.), vel: Vec2::ZERO }),
+        Box::new(Dipole::new(2., 1., 0.4, Vec2::new(5., 8.), Vec2::ZERO)),
+        Box::new(CircleCharge {
+            charge: -2., mass: 1., radius: 1.5, pos: Vec2::new(0., 12.), vel: Vec2::ZERO
+        })
+    ]
+}
+
+// Potential of the bodies together with their images, which is only defined in the physical
+// region, so it is found here from both directly.
+fn imaged_potential(bodies: &[Box<dyn Body>], boundary: &ImageBoundary, pos: Vec2) -> f32 {
+    bodies.potential(pos)+boundary.images(bodies)[..].potential(pos)
+}
+
+#[test]
+fn grounded_line_is_at_zero() {
+    let mut bodies = bodies();
+    let boundary = ImageBoundary::Line { point: Vec2::new(0., 2.), normal: Vec2::new(0.1, 1.) };
+
+    bodies.push(Box::new(LineCharge::new(Vec2::new(-6., 4.), Vec2::new(-1., 5.), 1.5)));
+
+    for i in -20..=20 {
+        let pos = Vec2::new(0., 2.)+i as f32*Vec2::new(1., -0.1);
+        let v = imaged_potential(&bodies, &boundary, pos);
+
+        assert!(v.abs() < 1e-4, "{} at {}", v, pos);
+    }
+}
+
+// Around a circle held at 0 or 3, or floating with a net charge of 1, the potential is the
+// same all the way round: the held value, or whatever the floating charge gives.
+#[test]
+fn circle_is_an_equipotential() {
+    let bodies = bodies();
+    let conditions = [
+        (Condition::Potential(0.), Some(0.)),
+        (Condition::Potential(3.), Some(3.)),
+        (Condition::Floating(1.), None)
+    ];
+
+    for (condition, held) in conditions {
+        let boundary = ImageBoundary::Circle { center: Vec2::ZERO, radius: 4., condition };
+        let around: Vec<f32> = (0..64).map(|i| {
+                                          let theta = TAU*i as f32/64.;
+                                          let pos = 4.*Vec2::new(theta.cos(), theta.sin());
+
+                                          imaged_potential(&bodies, &boundary, pos)
+                                      })
+                                      .collect();
+        let expected = held.unwrap_or(around[0]);
+
+        for v in around {
+            assert!((v-expected).abs() < 1e-4, "{} against {} for {:?}", v, expected, condition);
+        }
+    }
+}
+
+// The images inside a floating circle stand in for the charge on its surface, so they add
+// up to its net charge.
+#[test]
+fn floating_circle_keeps_its_charge() {
+    let bodies = bodies();
+    let boundary = ImageBoundary::Circle {
+        center: Vec2::ZERO, radius: 4., condition: Condition::Floating(1.)
+    };
+    let images = boundary.images(&bodies);
+    let image_charge: f32 = images.iter().map(|b| b.charge()).sum();
+
+    assert!((image_charge-1.).abs() < 1e-5, "{}", image_charge);
+}
+
+// Bodies in a group get the same images as they would on their own.
+#[test]
+fn groups_are_imaged_like_their_members() {
+    let line = ImageBoundary::Line { point: Vec2::new(0., 2.), normal: Vec2::Y };
+    let circle = ImageBoundary::Circle {
+        center: Vec2::ZERO, radius: 4., condition: Condition::Potential(1.)
+    };
+    let group: Vec<Box<dyn Body>> = vec![Box::new(bodies())];
+
+    for boundary in [line, circle] {
+        let (alone, grouped) = (boundary.images(&bodies()), boundary.images(&group));
+
+        for pos in [Vec2::new(3., 5.), Vec2::new(-7., 9.), Vec2::new(1., 20.)] {
+            let (a, b) = (alone[..].potential(pos), grouped[..].potential(pos));
+
+            assert!((a-b).abs() < 1e-5, "{} against {} at {}", a, b, pos);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Behaviour every integrator should share, whatever its order.
+
+use fieldsim::bodies::*;
+use fieldsim::simulation::{Integrator, Simulator};
+use fieldsim::Vec2;
+
+
+
+// A charge circling in a uniform magnetic field, with nothing else to do work on it.
+fn cyclotron() -> Vec<Box<dyn Body>> {
+    vec![
+        Box::new(GlobalMagneticField { field: 1. }),
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::ZERO, vel: Vec2::new(10., 0.) })
+    ]
+}
+
+#[test]
+fn magnetic_field_does_no_work() {
+    for integrator in Integrator::ALL {
+        let mut bodies = cyclotron();
+        let mut sim = Simulator::new(integrator).with_timestep(0.05);
+
+        sim.run(&mut bodies, 2000);
+
+        let speed = bodies[1].kinematics().vel.length();
+
+        assert!((speed-10.).abs() < 1e-2, "{}: speed {} after 2000 steps",
+                integrator.name(), speed);
+    }
+}
+
+// A few bodies pulling on each other, so that any difference in the steps taken shows up.
+fn cluster() -> Vec<Box<dyn Body>> {
+    vec![
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::new(-3., 0.), vel: Vec2::ZERO }),
+        Box::new(PointCharge { charge: -2., mass: 3., pos: Vec2::new(2., 1.), vel: Vec2::Y }),
+        Box::new(Dipole::new(1., 1., 0.5, Vec2::new(0., -4.), Vec2::ZERO)),
+        Box::new(LineCharge::new(Vec2::new(-1., 5.), Vec2::new(1., 6.), 1.)),
+        Box::new(GlobalMagneticField { field: 0.2 })
+    ]
+}
+
+fn run_frames(integrator: Integrator, frames: &[f32]) -> (u64, Vec<Kinematics>) {
+    let mut bodies = cluster();
+    let mut sim = Simulator::new(integrator).with_timestep(1./128.);
+
+    for &elapsed in frames { sim.advance(&mut bodies, elapsed); }
+
+    (sim.steps(), bodies.iter().map(|b| b.kinematics()).collect())
+}
+
+fn bits(k: &Kinematics) -> [u32; 6] {
+    [k.pos.x, k.pos.y, k.angle, k.vel.x, k.vel.y, k.ang_vel].map(f32::to_bits)
+}
+
+// The frame times are all multiples of the timestep with exact binary representations, so
+// that every split adds up to exactly the same number of steps.
+#[test]
+fn frame_times_do_not_change_the_result() {
+    let even = vec![1./64.; 64];
+    let uneven: Vec<f32> = [3./128., 1./256., 1./256., 1./16., 0., 1./32.].iter()
+                                                                          .cycle()
+                                                                          .take(48)
+                                                                          .copied()
+                                                                          .collect();
+    let single = vec![0.5, 0.5];
+
+    for integrator in Integrator::ALL {
+        let (steps, expected) = run_frames(integrator, &even);
+
+        assert_eq!(steps, 128);
+
+        for frames in [&uneven, &single] {
+            let (s, state) = run_frames(integrator, frames);
+
+            assert_eq!(s, steps, "{}", integrator.name());
+
+            for (a, b) in state.iter().zip(&expected) {
+                assert_eq!(bits(a), bits(b), "{}", integrator.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Field lines traced in the background.
+
+use fieldsim::bodies::*;
+use fieldsim::job::{Seeding, TraceJob};
+use fieldsim::scene::Scene;
+use fieldsim::util;
+use fieldsim::Vec2;
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+
+// A pair of opposite charges with an isopotential around each.
+fn pair() -> (Vec<Box<dyn Body>>, Vec<Vec<Vec2>>) {
+    let bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 2., mass: 1., pos: Vec2::new(-5., 0.), vel: Vec2::ZERO }),
+        Box::new(PointCharge { charge: -1., mass: 1., pos: Vec2::new(5., 0.), vel: Vec2::ZERO })
+    ];
+    let isopotentials = vec![
+        util::trace_isopotential(&bodies, Vec2::new(-3., 0.)),
+        util::trace_isopotential(&bodies, Vec2::new(3., 0.))
+    ];
+
+    (bodies, isopotentials)
+}
+
+// Lines come out as util::trace_field_lines or util::auto_field_lines would give them.
+#[test]
+fn job_traces_the_same_lines_as_a_direct_trace() {
+    let (bodies, isopotentials) = pair();
+
+    for seeding in [Seeding::Isopotentials, Seeding::Charges] {
+        let mut job = TraceJob::start(Scene::new(&bodies, &isopotentials, &[]), 1., seeding);
+        let started = Instant::now();
+        let expected = match seeding {
+            Seeding::Isopotentials => {
+                util::trace_field_lines(&bodies, &bodies, &isopotentials, 1.)
+            }
+            Seeding::Charges => util::auto_field_lines(&bodies, &bodies, 1.)
+        };
+
+        while !job.is_finished() {
+            assert!(started.elapsed() < Duration::from_secs(60));
+            thread::sleep(Duration::from_millis(5));
+            job.poll();
+        }
+
+        assert!(job.error().is_none());
+        assert_eq!(job.progress(), 1.);
+        assert_eq!(job.lines(), expected, "{:?}", seeding);
+    }
+}
+
+// Once cancelled, as dropping the job does, the worker sends nothing more than the lines it
+// was in the middle of, and never finishes.
+#[test]
+fn cancelled_job_stops_sending_lines() {
+    let (bodies, isopotentials) = pair();
+    let total = util::field_line_origins(&bodies, &isopotentials, 0.01).len();
+    let scene = Scene::new(&bodies, &isopotentials, &[]);
+    let mut job = TraceJob::start(scene, 0.01, Seeding::Isopotentials);
+
+    job.cancel();
+    thread::sleep(Duration::from_millis(500));
+    job.poll();
+
+    let settled = job.lines().len();
+
+    thread::sleep(Duration::from_millis(500));
+
+    assert!(!job.poll());
+    assert_eq!(job.lines().len(), settled);
+    assert!(settled < total && !job.is_finished());
+}
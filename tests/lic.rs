@@ -0,0 +1,105 @@
+// Line integral convolution textures, computed directly and in the background.
+
+use fieldsim::bodies::*;
+use fieldsim::canvas::Viewport;
+use fieldsim::job::LicJob;
+use fieldsim::lic::{Lic, LicStyle, LicTint};
+use fieldsim::scene::Scene;
+use fieldsim::Vec2;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+
+// Correlation between the brightness of pixels a given offset apart.
+fn correlation(lic: &Lic, (di, dj): (usize, usize)) -> f32 {
+    let w = lic.width() as usize;
+    let value = |i: usize, j: usize| lic.pixels()[j*w+i].r;
+    let pairs: Vec<(f32, f32)> = (0..lic.height() as usize-dj)
+        .flat_map(|j| (0..w-di).map(move |i| (i, j)))
+        .map(|(i, j)| (value(i, j), value(i+di, j+dj)))
+        .collect();
+    let n = pairs.len() as f32;
+    let (ma, mb) = pairs.iter().fold((0., 0.), |(a, b), (x, y)| (a+x/n, b+y/n));
+    let (mut ab, mut aa, mut bb) = (0., 0., 0.);
+
+    for (x, y) in pairs {
+        ab += (x-ma)*(y-mb);
+        aa += (x-ma)*(x-ma);
+        bb += (y-mb)*(y-mb);
+    }
+
+    ab/(aa*bb).sqrt()
+}
+
+// The noise is smeared along the field, so pixels a few apart along it look alike and
+// pixels the same distance across it don't.
+#[test]
+fn streaks_follow_the_field() {
+    let viewport = Viewport::new(80, 60);
+
+    for (field, along, across) in [(Vec2::X, (4, 0), (0, 4)), (Vec2::Y, (0, 4), (4, 0))] {
+        let lic = Lic::new(&GlobalField { field }, &viewport, LicStyle::default());
+
+        assert!(correlation(&lic, along) > 0.6, "{}", correlation(&lic, along));
+        assert!(correlation(&lic, across).abs() < 0.2, "{}", correlation(&lic, across));
+    }
+}
+
+#[test]
+fn texture_covers_the_viewport_and_is_repeatable() {
+    let charge = PointCharge { charge: 1., mass: 1., pos: Vec2::new(5., 3.), vel: Vec2::ZERO };
+    let viewport = Viewport::new(40, 30);
+
+    for tint in LicTint::ALL {
+        let style = LicStyle { tint, ..LicStyle::default() };
+        let (a, b) = (Lic::new(&charge, &viewport, style), Lic::new(&charge, &viewport, style));
+        let grey = a.pixels().iter().all(|c| c.r == c.g && c.g == c.b);
+
+        assert_eq!((a.width(), a.height()), (40, 30));
+        assert_eq!(a.to_rgba8().len(), 40*30*4);
+        assert_eq!(a.to_rgba8(), b.to_rgba8());
+        assert_eq!(grey, tint == LicTint::None, "{}", tint.name());
+    }
+}
+
+#[test]
+fn compute_counts_rows_and_stops_when_cancelled() {
+    let field = GlobalField { field: Vec2::X };
+    let viewport = Viewport::new(30, 20);
+    let style = LicStyle { tint: LicTint::Potential, ..LicStyle::default() };
+    let rows = AtomicUsize::new(0);
+
+    assert!(Lic::compute(&field, &viewport, style, &AtomicBool::new(false), &rows).is_some());
+    assert_eq!(rows.load(Ordering::Relaxed), Lic::total_rows(&viewport, style));
+    assert!(Lic::compute(&field, &viewport, style, &AtomicBool::new(true), &rows).is_none());
+}
+
+// A job gives the same texture as computing it directly, and goes stale when the field, the
+// viewport or the style changes.
+#[test]
+fn job_matches_a_direct_texture() {
+    let bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO })
+    ];
+    let scene = Scene::new(&bodies, &[], &[]);
+    let (viewport, style) = (Viewport::new(30, 20), LicStyle::default());
+    let mut job = LicJob::start(scene.clone(), viewport, style);
+    let started = Instant::now();
+    let lic = loop {
+        if let Some(result) = job.poll() { break result.unwrap(); }
+
+        assert!(started.elapsed() < Duration::from_secs(60));
+        thread::sleep(Duration::from_millis(5));
+    };
+    let mut moved = scene.clone();
+
+    moved.bodies = Scene::new(&[Box::new(GlobalField { field: Vec2::X })], &[], &[]).bodies;
+
+    assert_eq!(lic.to_rgba8(), Lic::new(&bodies, &viewport, style).to_rgba8());
+    assert!(job.is_finished() && !job.is_stale(&scene, &viewport, style));
+    assert!(job.is_stale(&moved, &viewport, style));
+    assert!(job.is_stale(&scene, &Viewport::new(30, 21), style));
+    assert!(job.is_stale(&scene, &viewport, LicStyle { length: 5., ..style }));
+}
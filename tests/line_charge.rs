@@ -0,0 +1,57 @@
+// Force and torque on a line charge, integrated over its length.
+
+use fieldsim::bodies::*;
+use fieldsim::Vec2;
+
+
+
+// A rod from (0, 1) to (4, 1) carrying a charge of 3, beside a charge of 2 at the origin.
+// With the field q r/r², the force along the rod integrates in closed form.
+#[test]
+fn force_and_torque_from_a_point_charge() {
+    let rod = LineCharge::new(Vec2::new(0., 1.), Vec2::new(4., 1.), 3.);
+    let charge = PointCharge { charge: 2., mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO };
+    let (force, torque) = rod.force_torque(&charge);
+    let k = 3./4.*2.;
+    let expected_force = k*Vec2::new(0.5*17f32.ln(), 4f32.atan());
+    // About the middle of the rod, at x = 2
+    let expected_torque = k*(0.5*17f32.ln()-2.*4f32.atan());
+
+    assert!(force.distance(expected_force) < 1e-3*expected_force.length(), "{}", force);
+    assert!((torque-expected_torque).abs() < 1e-3*expected_torque.abs(), "{}", torque);
+}
+
+// A uniform field pulls a rod along with the force QE, whichever way it lies, and has
+// nothing to turn it with.
+#[test]
+fn uniform_field_gives_no_torque() {
+    let field = GlobalField { field: Vec2::new(0.5, -1.) };
+
+    for angle in [0., 0.7, 2.] {
+        let along = 3.*Vec2::new(f32::cos(angle), f32::sin(angle));
+        let rod = LineCharge::new(Vec2::new(1., 1.)-along, Vec2::new(1., 1.)+along, -2.);
+        let (force, torque) = rod.force_torque(&field);
+
+        assert!(force.distance(-2.*field.field) < 1e-5 && torque.abs() < 1e-5);
+    }
+}
+
+// A rod given a mass moves as a thin uniform rod about its middle, of inertia mL²/12,
+// carrying its charge with it.
+#[test]
+fn moved_rod_carries_its_charge() {
+    let mut rod = LineCharge::new(Vec2::new(-3., 0.), Vec2::new(3., 0.), 1.)
+                             .with_motion(2., Vec2::ZERO, 0.);
+    let mut k = rod.kinematics();
+
+    k.pos = Vec2::new(5., 5.);
+    k.angle = std::f32::consts::FRAC_PI_2;
+    rod.set_kinematics(k);
+
+    let samples = rod.charge_samples();
+    let total: f32 = samples.iter().map(|(_, q)| q).sum();
+
+    assert!((rod.inertia()-2.*36./12.).abs() < 1e-5);
+    assert!((total-1.).abs() < 1e-5);
+    assert!(samples.iter().all(|(p, _)| (p.x-5.).abs() < 1e-5 && (p.y-5.).abs() <= 3.));
+}
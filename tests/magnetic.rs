@@ -0,0 +1,80 @@
+// Magnetic fields of currents through the plane, and the field lines traced from A_z. In the
+// units used throughout, μ₀/2π is 1, as 1/2πε₀ is for charges.
+
+use fieldsim::bodies::*;
+use fieldsim::util;
+use fieldsim::Vec2;
+
+
+
+fn wire(current: f32, pos: Vec2) -> CurrentWire {
+    CurrentWire { current, mass: 1., pos, vel: Vec2::ZERO }
+}
+
+// A_z = -μ₀I/2π ln r, and B is its curl, (dA_z/dy, -dA_z/dx).
+#[test]
+fn wire_vector_potential_and_field() {
+    let w = wire(2.5, Vec2::new(1., -1.));
+    let h = 1e-2;
+
+    for p in [Vec2::new(4., 3.), Vec2::new(-2., 0.5), Vec2::new(1., 6.)] {
+        let r = p.distance(w.pos);
+        let a = |dx: f32, dy: f32| w.vector_potential(p+Vec2::new(dx, dy));
+        let curl = Vec2::new(a(0., h)-a(0., -h), a(-h, 0.)-a(h, 0.))/(2.*h);
+        let b = w.b_field(p);
+
+        assert!((w.vector_potential(p)+2.5*r.ln()).abs() < 1e-5);
+        assert!(b.truncate().distance(curl) < 1e-3*b.length() && b.z == 0.);
+        assert_eq!(w.e_field(p), Vec2::ZERO);
+    }
+}
+
+// The field lines of a wire are closed circles around it.
+#[test]
+fn field_lines_circle_a_wire() {
+    let w = wire(-1., Vec2::ZERO);
+    let line = util::trace_isopotential(&VectorPotential(&w), Vec2::new(3., 0.));
+
+    assert_eq!(line.first(), line.last());
+    assert!(line.len() > 10);
+    assert!(line.iter().all(|p| (p.length()-3.).abs() < 1e-2));
+}
+
+// A sheet's A_z is that of the wires it is made of, summed along it.
+#[test]
+fn sheet_is_a_row_of_wires() {
+    let sheet = CurrentSheet::new(Vec2::new(-2., 0.), Vec2::new(2., 0.), 4.);
+    let n = 4000;
+    let wires: Vec<CurrentWire> = (0..n).map(|i| {
+                                            let x = -2.+4.*(i as f32+0.5)/n as f32;
+
+                                            wire(4./n as f32, Vec2::new(x, 0.))
+                                        })
+                                        .collect();
+
+    for p in [Vec2::new(0., 1.), Vec2::new(3., -2.), Vec2::new(-5., 0.5)] {
+        let a: f32 = wires.iter().map(|w| w.vector_potential(p)).sum();
+        let b = wires.iter().fold(Vec2::ZERO, |b, w| b+w.b_field(p).truncate());
+
+        assert!((sheet.vector_potential(p)-a).abs() < 1e-3, "{} at {}", a, p);
+        assert!(sheet.b_field(p).truncate().distance(b) < 1e-3*b.length(), "{} at {}", b, p);
+    }
+}
+
+// Parallel currents attract and antiparallel ones repel, while electric fields leave
+// currents alone.
+#[test]
+fn currents_feel_magnetic_forces_only() {
+    let other = wire(2., Vec2::new(4., 0.));
+
+    for (current, attract) in [(3., true), (-3., false)] {
+        let (force, _) = wire(current, Vec2::ZERO).force_torque(&other);
+
+        assert!((force.length()-6./4.).abs() < 1e-5);
+        assert_eq!(force.x > 0., attract);
+    }
+
+    let (force, _) = wire(1., Vec2::ZERO).force_torque(&GlobalField { field: Vec2::X });
+
+    assert_eq!(force, Vec2::ZERO);
+}
@@ -0,0 +1,153 @@
+// The grid Poisson solver against closed-form solutions.
+
+use fieldsim::bodies::*;
+use fieldsim::poisson::{self, Boundaries, Boundary, Grid};
+use fieldsim::scene::SceneBody;
+use fieldsim::simulation::{Integrator, Simulator};
+use fieldsim::Vec2;
+
+
+
+// Edges which hold the potential at either end and let no field cross the sides.
+fn channel(right: Boundary) -> Boundaries {
+    Boundaries {
+        left: Boundary::Dirichlet(0.),
+        right,
+        bottom: Boundary::Neumann(0.),
+        top: Boundary::Neumann(0.)
+    }
+}
+
+fn points(grid: &Grid) -> impl Iterator<Item = Vec2> + '_ {
+    let (lo, hi) = (grid.origin, grid.end());
+
+    (0..=20).flat_map(move |i| (0..=20).map(move |j| {
+        lo+(hi-lo)*Vec2::new(i as f32, j as f32)/20.
+    }))
+}
+
+// With no charge, a potential held at 0 and 10 on either end of a channel rises evenly
+// along it. Sweeps stop once they change the potential by little enough, which leaves it
+// within a thousandth or two of the largest potential of the exact solution.
+#[test]
+fn uniform_field_between_dirichlet_edges() {
+    let grid = Grid::covering(Vec2::ZERO, Vec2::new(20., 10.), 0.5);
+    let field = poisson::solve(grid, channel(Boundary::Dirichlet(10.)), Vec::new()).unwrap();
+
+    for p in points(&grid) {
+        assert!((field.potential(p)-p.x/2.).abs() < 2e-2, "{} at {}", field.potential(p), p);
+        assert!(field.e_field(p).distance(Vec2::new(-0.5, 0.)) < 1e-3, "{}", field.e_field(p));
+    }
+}
+
+// A Neumann edge fixes the slope of the potential at it, so here the potential rises at
+// that slope all the way from the grounded end, up to 5 at the far end.
+#[test]
+fn uniform_field_from_a_neumann_edge() {
+    let grid = Grid::covering(Vec2::ZERO, Vec2::new(20., 10.), 0.5);
+    let field = poisson::solve(grid, channel(Boundary::Neumann(0.25)), Vec::new()).unwrap();
+
+    for p in points(&grid) {
+        assert!((field.potential(p)-p.x/4.).abs() < 1e-2, "{} at {}", field.potential(p), p);
+        assert!(field.e_field(p).distance(Vec2::new(-0.25, 0.)) < 1e-3, "{}", field.e_field(p));
+    }
+}
+
+// Near a charge in the middle of a large grounded box, the walls only add a nearly
+// constant potential. By symmetry the first term that varies goes as (r/L)^4, so the
+// differences in potential and the field are those of the charge alone, -q ln r and q/r.
+#[test]
+fn point_charge_in_a_grounded_box() {
+    let q = 2.;
+    let sources: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: q, mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO })
+    ];
+    let grid = Grid::covering(Vec2::splat(-32.), Vec2::splat(32.), 0.5);
+    let field = poisson::solve(grid, Boundaries::default(), sources).unwrap();
+    let v0 = field.potential(Vec2::new(8., 0.));
+
+    for r in [4., 6., 10.] {
+        for dir in [Vec2::X, -Vec2::Y, Vec2::new(1., 1.).normalize()] {
+            let dv = field.potential(r*dir)-v0;
+            let e = field.e_field(r*dir);
+
+            assert!((dv+q*(r/8_f32).ln()).abs() < 1e-2, "{} at {}", dv, r*dir);
+            assert!(e.distance(q*dir/r) < 0.02*q/r, "{} at {}", e, r*dir);
+        }
+    }
+}
+
+// A source too strong for the sweeps to stay finite is reported rather than solved.
+#[test]
+fn diverging_grid_is_an_error() {
+    let body = SceneBody::PoissonGrid {
+        grid: Grid::covering(Vec2::splat(-8.), Vec2::splat(8.), 1.),
+        boundaries: Boundaries::default(),
+        sources: vec![
+            SceneBody::PointCharge { charge: 3e38, mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO }
+        ]
+    };
+
+    assert_eq!(body.into_body().err().as_deref(), Some("solution diverged"));
+}
+
+// A charge outside a grid feels only the other charges, as the grid's field stops at its
+// edge, both summed exactly and with a tree.
+#[test]
+fn charge_outside_a_grid_feels_nothing_from_it() {
+    let grid = Grid::covering(Vec2::ZERO, Vec2::new(20., 10.), 0.5);
+
+    for opening_angle in [None, Some(0.5)] {
+        let field = poisson::solve(grid, channel(Boundary::Dirichlet(10.)), Vec::new()).unwrap();
+        let charge = |y| {
+            PointCharge { charge: 1., mass: 1., pos: Vec2::new(-10., y), vel: Vec2::ZERO }
+        };
+        let mut bodies: Vec<Box<dyn Body>> = vec![
+            Box::new(field), Box::new(charge(0.)), Box::new(charge(4.))
+        ];
+        let mut simulator = Simulator::new(Integrator::VelocityVerlet);
+
+        simulator.opening_angle = opening_angle;
+        simulator.run(&mut bodies, 60);
+
+        let (a, b) = (bodies[1].kinematics(), bodies[2].kinematics());
+
+        assert!(a.vel.is_finite() && b.vel.is_finite(), "{} {}", a.vel, b.vel);
+        assert!(a.vel.x.abs() < 1e-6 && a.vel.y < 0., "{}", a.vel);
+        assert!((a.vel+b.vel).length() < 1e-5, "{} {}", a.vel, b.vel);
+    }
+}
+
+// The README's example grid, with two Neumann edges, converges in about 720 sweeps, and a
+// grounded box of the same size in about 340. Over-relaxation should keep the number of
+// sweeps growing no faster than the side of the grid.
+#[test]
+fn sweeps_to_converge() {
+    let example = channel(Boundary::Dirichlet(0.));
+
+    for (boundaries, most) in [(example, 800), (Boundaries::default(), 400)] {
+        let sources: Vec<Box<dyn Body>> = vec![
+            Box::new(PointCharge { charge: 10., mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO })
+        ];
+        let grid = Grid { origin: Vec2::splat(-128.), spacing: 1., nx: 257, ny: 257 };
+        let field = poisson::solve(grid, boundaries, sources).unwrap();
+
+        assert!(field.iterations() <= most, "{} sweeps", field.iterations());
+    }
+}
+
+// Timing only means anything with optimisations, and even then varies between machines, so
+// this only checks that the example takes well under a second, against the few tenths the
+// README gives.
+#[cfg(not(debug_assertions))]
+#[test]
+fn example_grid_solves_quickly() {
+    let sources: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 10., mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO })
+    ];
+    let grid = Grid { origin: Vec2::splat(-128.), spacing: 1., nx: 257, ny: 257 };
+    let start = std::time::Instant::now();
+
+    poisson::solve(grid, channel(Boundary::Dirichlet(0.)), sources).unwrap();
+    assert!(start.elapsed().as_secs_f32() < 1., "{:?}", start.elapsed());
+}
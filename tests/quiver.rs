@@ -0,0 +1,94 @@
+// Arrows showing the field on a grid, checked through what they draw.
+
+use fieldsim::bodies::*;
+use fieldsim::canvas::{Canvas, Color, Viewport};
+use fieldsim::quiver::{ArrowScaling, Quiver, QuiverStyle};
+use fieldsim::Vec2;
+
+
+
+// Keeps the middle and extent of every arrow drawn, from the tail of its shaft to its tip.
+#[derive(Default)]
+struct Arrows {
+    tails: Vec<Vec2>,
+    arrows: Vec<(Vec2, Vec2)>
+}
+
+impl Canvas for Arrows {
+    fn ellipse(&mut self, _center: Vec2, _radius: f32, _color: Color) {}
+
+    fn line(&mut self, start: Vec2, _end: Vec2, _weight: f32, _color: Color) {
+        self.tails.push(start);
+    }
+
+    fn tri(&mut self, points: [Vec2; 3], _color: Color) {
+        let tail = self.tails.pop().unwrap();
+
+        self.arrows.push(((tail+points[0])/2., points[0]-tail));
+    }
+
+    fn polyline(&mut self, _points: &[Vec2], _weight: f32, _color: Color) {}
+}
+
+// Distance from a point to the nearest grid point, in grid spacings.
+fn off_grid(pos: Vec2, spacing: f32) -> f32 {
+    (pos/spacing).round().distance(pos/spacing)
+}
+
+fn arrows(body: &impl Field, style: QuiverStyle) -> Vec<(Vec2, Vec2)> {
+    let mut canvas = Arrows::default();
+
+    Quiver::new(body, &Viewport::new(200, 100), style).draw(&mut canvas);
+    assert!(canvas.tails.is_empty());
+    canvas.arrows
+}
+
+// One arrow on every grid point covering the viewport, all pointing along the field.
+#[test]
+fn arrows_follow_a_uniform_field() {
+    let field = GlobalField { field: Vec2::new(1., 2.) };
+    let style = QuiverStyle {
+        spacing: 20.,
+        scaling: ArrowScaling::Normalised,
+        ..Default::default()
+    };
+    let arrows = arrows(&field, style);
+
+    assert_eq!(arrows.len(), 11*7);
+
+    for (pos, v) in arrows {
+        assert!(off_grid(pos, 20.) < 1e-4);
+        assert!((v.length()-16.).abs() < 1e-3);
+        assert!(v.normalize().distance(field.field.normalize()) < 1e-5);
+    }
+}
+
+// Close to a charge arrows point straight out of it, and grow no longer than the longest
+// arrow however strong the field gets.
+#[test]
+fn arrows_near_a_charge_are_clamped() {
+    let charge = PointCharge { charge: 1., mass: 1., pos: Vec2::new(5., 5.), vel: Vec2::ZERO };
+
+    for scaling in ArrowScaling::ALL {
+        let style = QuiverStyle { spacing: 10., scaling, ..Default::default() };
+
+        for (pos, v) in arrows(&charge, style) {
+            let out = (pos-charge.pos).normalize();
+
+            assert!(v.length() <= 8.+1e-4, "{} at {} with {}", v, pos, scaling.name());
+            assert!(v.normalize().distance(out) < 1e-4);
+        }
+    }
+}
+
+// Jittered arrows stay near their grid points, and in the same place every time.
+#[test]
+fn jitter_is_fixed() {
+    let field = GlobalField { field: Vec2::X };
+    let style = QuiverStyle { spacing: 20., jitter: true, ..Default::default() };
+    let (first, second) = (arrows(&field, style), arrows(&field, style));
+
+    assert_eq!(first, second);
+    assert!(first.iter().all(|(pos, _)| off_grid(*pos, 20.) <= 0.4*2f32.sqrt()));
+    assert!(first.iter().any(|(pos, _)| off_grid(*pos, 20.) > 0.1));
+}
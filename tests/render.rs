@@ -0,0 +1,134 @@
+// The headless renderer, run as its own process the way scripts run it.
+
+use fieldsim::bodies::*;
+use fieldsim::images::{ImageBoundary, DRAWN_SAMPLES};
+use fieldsim::scene::Scene;
+use fieldsim::Vec2;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::process::Command;
+use tiny_skia::Pixmap;
+
+
+
+// Save a scene, render it to a file of the given type and return the file's path.
+fn render_scene(scene: &Scene, name: &str, extension: &str, options: &[&str]) -> PathBuf {
+    let file = format!("fieldsim-render-{}-{}.ron", std::process::id(), name);
+    let scene_path = std::env::temp_dir().join(file);
+    let output = scene_path.with_extension(extension);
+
+    scene.save(&scene_path).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_fieldsim-render"))
+                         .arg(&scene_path)
+                         .arg(&output)
+                         .args(options)
+                         .status()
+                         .unwrap();
+
+    std::fs::remove_file(&scene_path).unwrap();
+    assert!(status.success());
+    output
+}
+
+// Render a unit charge at the origin with an isopotential through (30, 0).
+fn render(name: &str, extension: &str, options: &[&str]) -> PathBuf {
+    let bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 1., mass: 1., pos: Vec2::ZERO, vel: Vec2::ZERO })
+    ];
+
+    render_scene(&Scene::new(&bodies, &[vec![Vec2::new(30., 0.)]], &[]), name, extension, options)
+}
+
+#[test]
+fn svg_has_a_polyline_for_every_curve_and_a_glyph_for_every_body() {
+    let path = render("svg", "svg", &["--size", "200x100", "--flux", "1"]);
+    let svg = std::fs::read_to_string(&path).unwrap();
+    let polylines = |color: &str| -> Vec<&str> {
+        svg.lines().filter(|l| l.starts_with("<polyline") && l.contains(color)).collect()
+    };
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(svg.starts_with("<svg") && svg.contains(r#"width="200" height="100""#));
+
+    // The isopotential is drawn in white, retraced all the way round the charge, which is
+    // at the centre of the image
+    let isopotentials = polylines("rgb(255,255,255)");
+    let points = isopotentials[0].split('"').nth(1).unwrap();
+
+    assert_eq!(isopotentials.len(), 1);
+
+    for p in points.split(' ') {
+        let (x, y) = p.split_once(',').unwrap();
+        let r = Vec2::new(x.parse().unwrap(), y.parse().unwrap()).distance(Vec2::new(100., 50.));
+
+        assert!((r-30.).abs() < 0.1, "point {} is {} from the charge", p, r);
+    }
+
+    assert!(polylines("rgb(255,255,0)").len() >= 4);
+    assert!(svg.contains(r#"<circle cx="100.00" cy="50.00""#));
+}
+
+#[test]
+fn png_is_drawn_at_the_requested_size() {
+    let path = render("png", "png", &["--size", "300x200", "--scale", "2"]);
+    let image = Pixmap::load_png(&path).unwrap();
+    let pixel = |x, y| image.pixel(x, y).unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((image.width(), image.height()), (300, 200));
+
+    // A black background, the red glyph of the charge off its white cross, and the white
+    // isopotential 60 pixels from it
+    assert_eq!(pixel(0, 0), pixel(299, 199));
+    assert_eq!((pixel(0, 0).red(), pixel(0, 0).green(), pixel(0, 0).blue()), (0, 0, 0));
+    assert!(pixel(152, 102).red() > 200 && pixel(152, 102).green() < 50);
+    assert!((205..215).any(|x| pixel(x, 100).blue() > 200));
+}
+
+// Close to the charge the potential is far above the median, which the heat map shows red.
+#[test]
+fn png_heat_map_is_drawn_behind_the_scene() {
+    let path = render("heatmap", "png", &["--size", "300x200", "--scale", "2", "--heatmap"]);
+    let image = Pixmap::load_png(&path).unwrap();
+    let near = image.pixel(170, 120).unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(near.red() > 100 && near.blue() == 0);
+}
+
+// A charge q at height h above a grounded line draws -q h/(π(s²+h²)) per unit length onto
+// it, s along the line from the foot of the charge.
+#[test]
+fn surface_charge_is_written_along_the_boundary() {
+    let (q, h) = (2., 10.);
+    let bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: q, mass: 1., pos: Vec2::new(0., h), vel: Vec2::ZERO })
+    ];
+    let mut scene = Scene::new(&bodies, &[], &[]);
+    let csv_path = std::env::temp_dir()
+        .join(format!("fieldsim-render-{}-surface.csv", std::process::id()));
+
+    scene.boundary = Some(ImageBoundary::Line { point: Vec2::ZERO, normal: Vec2::Y });
+
+    let options = ["--surface-charge", csv_path.to_str().unwrap()];
+    let svg = render_scene(&scene, "surface", "svg", &options);
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    let rows: Vec<Vec<f32>> = csv.lines()
+                                 .skip(1)
+                                 .map(|l| l.split(',').map(|x| x.parse().unwrap()).collect())
+                                 .collect();
+
+    std::fs::remove_file(&svg).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    assert!(csv.starts_with("distance,x,y,charge\n"));
+    assert_eq!(rows.len(), DRAWN_SAMPLES);
+
+    for row in rows {
+        let (s, x, y, charge) = (row[0], row[1], row[2], row[3]);
+        let expected = -q*h/(PI*(s*s+h*h));
+
+        assert!((x+s).abs() < 1e-2 && y == 0., "{:?}", row);
+        assert!((charge-expected).abs() < 1e-4, "{} against {} at {}", charge, expected, s);
+    }
+}
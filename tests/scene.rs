@@ -0,0 +1,82 @@
+// Reading and writing scene files.
+
+use fieldsim::bodies::*;
+use fieldsim::scene::{Scene, SceneError, SCENE_VERSION};
+use fieldsim::Vec2;
+
+
+
+// A file written by a newer build may use fields and bodies this one has never heard of,
+// which must not get in the way of saying that it is too new.
+#[test]
+fn newer_files_give_a_version_error() {
+    let text = format!(
+        "(version: {}, bodies: [Quasar(spin: 2.0), PointCharge(charge: 1.0, pos: (0.0, 0.0))], \
+         wormholes: Some([(1.0, 2.0)]))",
+        SCENE_VERSION+1
+    );
+
+    match Scene::parse(&text) {
+        Err(SceneError::UnsupportedVersion(v)) => assert_eq!(v, SCENE_VERSION+1),
+        other => panic!("expected a version error, got {:?}", other)
+    }
+}
+
+// The first version had only bodies and traced lines, with everything since defaulted.
+#[test]
+fn first_version_still_loads() {
+    let text = "(version: 1, bodies: [PointCharge(charge: 1.0, pos: (1.0, 2.0))], \
+                isopotentials: [[(0.0, 1.0), (1.0, 0.0)]])";
+    let scene = Scene::parse(text).unwrap();
+
+    assert_eq!(scene.bodies.len(), 1);
+    assert_eq!(scene.isopotentials.len(), 1);
+    assert!(scene.boundary.is_none() && scene.magnetic_lines.is_empty());
+}
+
+#[test]
+fn saved_scenes_round_trip_at_the_current_version() {
+    let bodies: Vec<Box<dyn Body>> = vec![
+        Box::new(PointCharge { charge: 2., mass: 1., pos: Vec2::new(1., 2.), vel: Vec2::X }),
+        Box::new(CurrentWire { current: 1., mass: 1., pos: -3.*Vec2::X, vel: Vec2::ZERO })
+    ];
+    let scene = Scene::new(&bodies, &[], &[vec![Vec2::ZERO, Vec2::ONE]]);
+    let text = scene.to_ron().unwrap();
+
+    assert!(text.contains(&format!("version: {}", SCENE_VERSION)));
+    assert_eq!(Scene::parse(&text).unwrap(), scene);
+}
+
+#[test]
+fn unknown_fields_are_rejected_at_the_current_version() {
+    let text = format!("(version: {}, bodies: [], wormholes: [])", SCENE_VERSION);
+
+    assert!(matches!(Scene::parse(&text), Err(SceneError::Parse(_))));
+}
+
+// Only charges can be imaged in a boundary, however deep in a group they are, so anything
+// else alongside one is rejected, both when parsed and when built in code.
+#[test]
+fn bodies_without_images_are_rejected_with_a_boundary() {
+    let charge = "PointCharge(charge: 1.0, pos: (0.0, 5.0))";
+    let wire = "CurrentWire(current: 1.0, pos: (3.0, 5.0))";
+    let boundary = "boundary: Some(Line(point: (0.0, 0.0), normal: (0.0, 1.0)))";
+    let parse = |bodies: &str, boundary: &str| {
+        Scene::parse(&format!("(version: {}, bodies: [{}], {})", SCENE_VERSION, bodies, boundary))
+    };
+
+    assert!(parse(&format!("{}, Group([{}])", charge, charge), boundary).is_ok());
+    assert!(parse(&format!("{}, {}", charge, wire), "").is_ok());
+
+    for bodies in [format!("{}, {}", charge, wire), format!("{}, Group([{}])", charge, wire)] {
+        let mut scene = parse(&bodies, "").unwrap();
+
+        match parse(&bodies, boundary) {
+            Err(SceneError::InvalidBody { index, .. }) => assert_eq!(index, 1),
+            other => panic!("expected an invalid body, got {:?}", other)
+        }
+
+        scene.boundary = parse("", boundary).unwrap().boundary;
+        assert!(matches!(scene.bodies(), Err(SceneError::InvalidBody { index: 1, .. })));
+    }
+}
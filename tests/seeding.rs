@@ -0,0 +1,89 @@
+// Field lines seeded straight from the charges, flux_step of flux apart.
+
+use fieldsim::bodies::*;
+use fieldsim::util::{self, LineEnd};
+use fieldsim::Vec2;
+use std::f32::consts::{PI, TAU};
+
+
+
+fn charge(q: f32, pos: Vec2) -> Box<dyn Body> {
+    Box::new(PointCharge { charge: q, mass: 1., pos, vel: Vec2::ZERO })
+}
+
+// Lines with an end on the charge at pos, which they stop just short of.
+fn ending_at(lines: &[Vec<Vec2>], pos: Vec2) -> usize {
+    lines.iter()
+         .filter(|l| [l[0], l[l.len()-1]].iter().any(|p| p.distance(pos) < 0.3))
+         .count()
+}
+
+// A charge of 2 sends out twice as many lines as a charge of -1 takes in, and every one of
+// the negative charge's lines is drawn, whether it comes from the positive charge or not.
+#[test]
+fn lines_are_in_proportion_to_flux() {
+    let (a, b) = (Vec2::new(-3., 0.), Vec2::new(3., 0.));
+    let bodies = [charge(2., a), charge(-1., b)];
+    let flux_step = TAU/8.;
+    let (positive, negative) = util::charge_seeds(&bodies, flux_step);
+
+    assert_eq!((positive.len(), negative.len()), (16, 8));
+
+    let lines = util::auto_field_lines(&bodies[..], &bodies, flux_step);
+
+    assert_eq!(ending_at(&lines, a), 16);
+    assert!((8..=9).contains(&ending_at(&lines, b)), "{}", ending_at(&lines, b));
+}
+
+// Of the twelve lines from the positive charge of a neutral pair, all but the one heading
+// straight away from the negative charge reach it. That charge then only needs a line of
+// its own on the far side, rather than having every line drawn twice.
+#[test]
+fn reached_seeds_are_not_traced_again() {
+    let bodies = [charge(1., Vec2::new(-1., 0.)), charge(-1., Vec2::new(1., 0.))];
+    let flux_step = TAU/12.;
+    let lines = util::auto_field_lines(&bodies[..], &bodies, flux_step);
+
+    assert_eq!(lines.len(), 13);
+    assert_eq!(ending_at(&lines, Vec2::new(-1., 0.)), 12);
+    assert_eq!(ending_at(&lines, Vec2::new(1., 0.)), 12);
+}
+
+// Lines arriving at the same place claim different seeds, so a seed is never skipped for
+// two lines, and a charge keeps as many lines as it has seeds.
+#[test]
+fn each_arriving_line_claims_its_own_seed() {
+    let bodies = [charge(-1., Vec2::ZERO)];
+    let (_, seeds) = util::charge_seeds(&bodies, TAU/6.);
+    // Traced lines stop just outside the charge, on a disc of radius 0.2
+    let arriving = |p: Vec2| (vec![p, 2.*p], [LineEnd::Charge, LineEnd::Boundary]);
+    let near_first = seeds[0].1.normalize()*0.2005;
+
+    assert_eq!(seeds.len(), 6);
+    assert_eq!(util::unreached_seeds(&bodies, &seeds, &[arriving(near_first)]).len(), 5);
+
+    let lines = [arriving(near_first), arriving(near_first)];
+    let rest = util::unreached_seeds(&bodies, &seeds, &lines);
+
+    assert_eq!(rest.len(), 4);
+    assert!(!rest.contains(&seeds[0].1));
+}
+
+// A line charge's seeds are spread evenly along both sides at its charge density, so a line
+// three times as long with the same density gets three times as many.
+#[test]
+fn line_charges_are_seeded_by_charge_density() {
+    let flux_step = PI/6.;
+    let short = LineCharge::new(Vec2::new(0., 0.), Vec2::new(2., 0.), 1.);
+    let long = LineCharge::new(Vec2::new(0., 5.), Vec2::new(6., 5.), 3.);
+    let (short, long) = (short.field_line_seeds(flux_step), long.field_line_seeds(flux_step));
+
+    assert_eq!(short.len(), 12);
+    assert_eq!(long.len(), 3*short.len());
+
+    // The seeds on one side are a constant distance apart
+    let above: Vec<f32> = long.iter().filter(|(p, _)| p.y > 5.).map(|(p, _)| p.x).collect();
+    let spacing = 6./above.len() as f32;
+
+    assert!(above.windows(2).all(|w| (w[1]-w[0]-spacing).abs() < 1e-4), "{:?}", above);
+}
@@ -0,0 +1,48 @@
+// Tracing in parallel against tracing one line at a time.
+
+use fieldsim::bodies::*;
+use fieldsim::util;
+use fieldsim::Vec2;
+
+
+
+fn scene() -> Vec<Box<dyn Body>> {
+    vec![
+        Box::new(PointCharge { charge: 2., mass: 1., pos: Vec2::new(-4., 1.), vel: Vec2::ZERO }),
+        Box::new(PointCharge { charge: -1., mass: 1., pos: Vec2::new(3., 0.), vel: Vec2::ZERO }),
+        Box::new(Dipole::new(1., 1., 0.7, Vec2::new(0., -5.), Vec2::ZERO)),
+        Box::new(LineCharge::new(Vec2::new(-3., 6.), Vec2::new(2., 7.), -3.))
+    ]
+}
+
+fn starts() -> Vec<Vec2> {
+    (0..12).map(|i| Vec2::new(-6.+i as f32, 0.5*(i as f32).sin())).collect()
+}
+
+#[test]
+fn parallel_isopotentials_match_serial() {
+    let bodies = scene();
+    let field = &bodies[..];
+    let serial: Vec<Vec<Vec2>> = starts().iter()
+                                         .map(|r0| util::trace_isopotential(field, *r0))
+                                         .collect();
+
+    assert_eq!(util::trace_isopotentials(field, &starts()), serial);
+}
+
+#[test]
+fn parallel_field_lines_match_serial() {
+    let bodies = scene();
+    let field = &bodies[..];
+    let isopotentials = util::trace_isopotentials(field, &starts()[..4]);
+    let origins: Vec<Vec2> = isopotentials.iter()
+                                          .filter(|i| !i.is_empty())
+                                          .flat_map(|i| util::divide_isopotential(field, i, 2.))
+                                          .collect();
+    let serial: Vec<Vec<Vec2>> = origins.iter()
+                                        .map(|r0| util::trace_field_line(field, field, *r0).0)
+                                        .collect();
+
+    assert!(serial.len() > 10);
+    assert_eq!(util::trace_field_lines(field, field, &isopotentials, 2.), serial);
+}